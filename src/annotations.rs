@@ -0,0 +1,236 @@
+use crate::raster::Canvas;
+use eframe::egui;
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, Instant};
+
+/// How long a stroke takes to fade out once its time is up.
+const FADE_OUT: Duration = Duration::from_millis(500);
+/// Freehand points closer than this, as a fraction of the frame width, are skipped.
+const MIN_POINT_DISTANCE: f32 = 0.002;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum AnnotationTool {
+    Pen,
+    Highlighter,
+    Arrow,
+    Rectangle,
+}
+
+impl AnnotationTool {
+    pub const ALL: [AnnotationTool; 4] = [
+        AnnotationTool::Pen,
+        AnnotationTool::Highlighter,
+        AnnotationTool::Arrow,
+        AnnotationTool::Rectangle,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            AnnotationTool::Pen => "Pen",
+            AnnotationTool::Highlighter => "Highlighter",
+            AnnotationTool::Arrow => "Arrow",
+            AnnotationTool::Rectangle => "Rectangle",
+        }
+    }
+
+    /// Freehand tools keep every point, shapes only the start and end.
+    fn is_freehand(&self) -> bool {
+        matches!(self, AnnotationTool::Pen | AnnotationTool::Highlighter)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AnnotationSettings {
+    pub tool: AnnotationTool,
+    pub color: [u8; 3],
+    /// Line width in video pixels, the highlighter draws three times as wide.
+    pub width: f32,
+    pub fade: bool,
+    /// Seconds a finished stroke stays before fading out.
+    pub fade_after: f32,
+}
+
+impl Default for AnnotationSettings {
+    fn default() -> Self {
+        Self {
+            tool: AnnotationTool::Pen,
+            color: [255, 80, 80],
+            width: 6.0,
+            fade: false,
+            fade_after: 3.0,
+        }
+    }
+}
+
+struct Stroke {
+    tool: AnnotationTool,
+    /// Fractions of the frame, so strokes survive resolution changes.
+    points: Vec<[f32; 2]>,
+    color: [u8; 3],
+    width: f32,
+    /// None while the stroke is still being drawn.
+    finished: Option<Instant>,
+}
+
+impl Stroke {
+    /// 1 until the stroke's time is up, then fading to 0.
+    fn opacity(&self, fade_after: Option<Duration>) -> f32 {
+        match (self.finished, fade_after) {
+            (Some(finished), Some(fade_after)) => {
+                let fading = finished.elapsed().saturating_sub(fade_after);
+                1.0 - (fading.as_secs_f32() / FADE_OUT.as_secs_f32()).min(1.0)
+            }
+            _ => 1.0,
+        }
+    }
+
+    fn draw(&self, canvas: &mut Canvas, width: usize, height: usize, opacity: f32) {
+        let points: Vec<[f32; 2]> = self
+            .points
+            .iter()
+            .map(|p| [p[0] * width as f32, p[1] * height as f32])
+            .collect();
+        let [r, g, b] = self.color;
+        let alpha = |a: f32| (a * opacity * 255.0).round() as u8;
+
+        match self.tool {
+            AnnotationTool::Pen => canvas.stroke(&[&points], self.width, [r, g, b, alpha(1.0)]),
+            AnnotationTool::Highlighter => {
+                canvas.stroke(&[&points], self.width * 3.0, [r, g, b, alpha(0.35)])
+            }
+            AnnotationTool::Arrow => {
+                let (Some(&start), Some(&tip)) = (points.first(), points.last()) else {
+                    return;
+                };
+                let d = [tip[0] - start[0], tip[1] - start[1]];
+                let length = (d[0] * d[0] + d[1] * d[1]).sqrt();
+                if length < 1.0 {
+                    return;
+                }
+                // Head sides at 30 degrees from the shaft, scaled with the width
+                let head = (self.width * 4.0).min(length / 2.0);
+                let (ux, uy) = (d[0] / length, d[1] / length);
+                let (cos, sin) = (30f32.to_radians().cos(), 30f32.to_radians().sin());
+                let side = |sign: f32| {
+                    [
+                        tip[0] - head * (ux * cos - sign * uy * sin),
+                        tip[1] - head * (uy * cos + sign * ux * sin),
+                    ]
+                };
+                canvas.stroke(
+                    &[&[start, tip], &[side(1.0), tip, side(-1.0)]],
+                    self.width,
+                    [r, g, b, alpha(1.0)],
+                );
+            }
+            AnnotationTool::Rectangle => {
+                let (Some(&start), Some(&end)) = (points.first(), points.last()) else {
+                    return;
+                };
+                let corners = [start, [end[0], start[1]], end, [start[0], end[1]], start];
+                canvas.stroke(&[&corners], self.width, [r, g, b, alpha(1.0)]);
+            }
+        }
+    }
+}
+
+/// Strokes drawn over the main video, baked into each frame by the frame callback.
+#[derive(Default)]
+pub struct Annotations {
+    strokes: Vec<Stroke>,
+    /// Finished strokes fade out after this long, or stay until cleared.
+    pub fade_after: Option<Duration>,
+}
+
+impl Annotations {
+    /// Start a stroke at `at`, a fraction of the frame.
+    pub fn begin(&mut self, settings: &AnnotationSettings, at: [f32; 2]) {
+        self.finish();
+        self.strokes.push(Stroke {
+            tool: settings.tool,
+            points: vec![at],
+            color: settings.color,
+            width: settings.width,
+            finished: None,
+        });
+    }
+
+    /// Continue the stroke being drawn to `to`.
+    pub fn extend(&mut self, to: [f32; 2]) {
+        let Some(stroke) = self.strokes.last_mut().filter(|s| s.finished.is_none()) else {
+            return;
+        };
+        if stroke.tool.is_freehand() {
+            let last = stroke.points[stroke.points.len() - 1];
+            if (to[0] - last[0]).hypot(to[1] - last[1]) >= MIN_POINT_DISTANCE {
+                stroke.points.push(to);
+            }
+        } else {
+            stroke.points.truncate(1);
+            stroke.points.push(to);
+        }
+    }
+
+    pub fn finish(&mut self) {
+        if let Some(stroke) = self.strokes.last_mut() {
+            stroke.finished.get_or_insert_with(Instant::now);
+        }
+    }
+
+    pub fn undo(&mut self) {
+        self.strokes.pop();
+    }
+
+    pub fn clear(&mut self) {
+        self.strokes.clear();
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.strokes.is_empty()
+    }
+
+    pub fn draw(&mut self, canvas: &mut Canvas, width: usize, height: usize) {
+        let fade_after = self.fade_after;
+        self.strokes
+            .retain(|stroke| stroke.opacity(fade_after) > 0.0);
+        for stroke in &self.strokes {
+            stroke.draw(canvas, width, height, stroke.opacity(fade_after));
+        }
+    }
+}
+
+/// Tool, color, width and fade controls, returns true when anything changed.
+pub fn controls(ui: &mut egui::Ui, settings: &mut AnnotationSettings) -> bool {
+    let mut changed = false;
+    ui.horizontal(|ui| {
+        for tool in AnnotationTool::ALL {
+            changed |= ui
+                .selectable_value(&mut settings.tool, tool, tool.label())
+                .changed();
+        }
+    });
+    ui.horizontal(|ui| {
+        changed |= ui.color_edit_button_srgb(&mut settings.color).changed();
+        changed |= ui
+            .add(
+                egui::Slider::new(&mut settings.width, 1.0..=40.0)
+                    .suffix(" px")
+                    .text("Width"),
+            )
+            .changed();
+    });
+    ui.horizontal(|ui| {
+        changed |= ui.checkbox(&mut settings.fade, "Fade out after").changed();
+        changed |= ui
+            .add_enabled(
+                settings.fade,
+                egui::DragValue::new(&mut settings.fade_after)
+                    .range(0.5..=30.0)
+                    .speed(0.1)
+                    .suffix(" s"),
+            )
+            .changed();
+    });
+    changed
+}
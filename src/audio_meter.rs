@@ -0,0 +1,140 @@
+use eframe::egui;
+use gstreamer as gst;
+use gstreamer::glib;
+use gstreamer::prelude::*;
+use std::time::{Duration, Instant};
+
+/// Anything quieter than this is drawn as silence.
+const METER_FLOOR_DB: f64 = -60.0;
+/// Peaks at or above this are considered clipping.
+const CLIP_DB: f64 = -0.5;
+const CLIP_HOLD: Duration = Duration::from_millis(1500);
+const PEAK_DECAY_DB_PER_SEC: f64 = 20.0;
+
+/// Latest readings from a GStreamer `level` element.
+#[derive(Debug)]
+pub struct AudioLevel {
+    pub rms_db: f64,
+    pub peak_db: f64,
+    held_peak_db: f64,
+    last_update: Instant,
+    clipped_at: Option<Instant>,
+}
+
+impl Default for AudioLevel {
+    fn default() -> Self {
+        Self {
+            rms_db: METER_FLOOR_DB,
+            peak_db: METER_FLOOR_DB,
+            held_peak_db: METER_FLOOR_DB,
+            last_update: Instant::now(),
+            clipped_at: None,
+        }
+    }
+}
+
+impl AudioLevel {
+    /// Update from a bus message posted by the `level` element named `element_name`,
+    /// returns false if the message was something else.
+    pub fn update_from_message(&mut self, msg: &gst::Message, element_name: &str) -> bool {
+        let gst::MessageView::Element(element) = msg.view() else {
+            return false;
+        };
+        if msg
+            .src()
+            .map(|src| src.name().as_str() != element_name)
+            .unwrap_or(true)
+        {
+            return false;
+        }
+        let Some(s) = element.structure() else {
+            return false;
+        };
+        if s.name() != "level" {
+            return false;
+        }
+
+        // One value per channel, the loudest channel drives the meter
+        let loudest = |field: &str| -> Option<f64> {
+            let values = s.get::<glib::ValueArray>(field).ok()?;
+            values
+                .iter()
+                .filter_map(|v| v.get::<f64>().ok())
+                .reduce(f64::max)
+        };
+
+        let now = Instant::now();
+        let elapsed = now.duration_since(self.last_update).as_secs_f64();
+        self.last_update = now;
+
+        if let Some(rms) = loudest("rms") {
+            self.rms_db = rms.max(METER_FLOOR_DB);
+        }
+        if let Some(peak) = loudest("peak") {
+            self.peak_db = peak.max(METER_FLOOR_DB);
+            self.held_peak_db =
+                (self.held_peak_db - PEAK_DECAY_DB_PER_SEC * elapsed).max(self.peak_db);
+            if peak >= CLIP_DB {
+                self.clipped_at = Some(now);
+            }
+        }
+
+        true
+    }
+
+    pub fn reset(&mut self) {
+        *self = Self::default();
+    }
+
+    pub fn is_clipping(&self) -> bool {
+        self.clipped_at
+            .map(|at| at.elapsed() < CLIP_HOLD)
+            .unwrap_or(false)
+    }
+}
+
+fn db_to_fraction(db: f64) -> f32 {
+    ((db - METER_FLOOR_DB) / -METER_FLOOR_DB).clamp(0.0, 1.0) as f32
+}
+
+/// Horizontal VU meter: RMS bar, decaying peak marker and a clip highlight.
+pub fn vu_meter(ui: &mut egui::Ui, level: &AudioLevel, size: egui::Vec2) -> egui::Response {
+    let (rect, response) = ui.allocate_exact_size(size, egui::Sense::hover());
+    let painter = ui.painter_at(rect);
+    let rounding = size.y / 2.0;
+
+    painter.rect_filled(rect, rounding, egui::Color32::from_rgb(40, 40, 50));
+
+    let rms = db_to_fraction(level.rms_db);
+    let color = if level.is_clipping() {
+        egui::Color32::from_rgb(255, 80, 80)
+    } else if level.rms_db > -12.0 {
+        egui::Color32::from_rgb(240, 200, 80)
+    } else {
+        egui::Color32::from_rgb(100, 220, 100)
+    };
+    let bar = egui::Rect::from_min_size(rect.min, egui::vec2(rect.width() * rms, rect.height()));
+    painter.rect_filled(bar, rounding, color);
+
+    let peak_x = rect.left() + rect.width() * db_to_fraction(level.held_peak_db);
+    painter.line_segment(
+        [
+            egui::pos2(peak_x, rect.top()),
+            egui::pos2(peak_x, rect.bottom()),
+        ],
+        egui::Stroke::new(1.5, egui::Color32::WHITE),
+    );
+
+    if level.is_clipping() {
+        painter.rect_stroke(
+            rect,
+            rounding,
+            egui::Stroke::new(1.0, egui::Color32::from_rgb(255, 80, 80)),
+        );
+    }
+
+    response.on_hover_text(format!(
+        "RMS {:.1} dB, peak {:.1} dB",
+        level.rms_db, level.peak_db
+    ))
+}
@@ -0,0 +1,69 @@
+/// Containers for audio-only recordings, where no video is captured at all.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AudioFormat {
+    Opus,
+    Flac,
+    Wav,
+    M4a,
+}
+
+impl AudioFormat {
+    pub const ALL: [AudioFormat; 4] = [
+        AudioFormat::Opus,
+        AudioFormat::Flac,
+        AudioFormat::Wav,
+        AudioFormat::M4a,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            AudioFormat::Opus => "Opus (OGG)",
+            AudioFormat::Flac => "FLAC",
+            AudioFormat::Wav => "WAV",
+            AudioFormat::M4a => "AAC (M4A)",
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            AudioFormat::Opus => "ogg",
+            AudioFormat::Flac => "flac",
+            AudioFormat::Wav => "wav",
+            AudioFormat::M4a => "m4a",
+        }
+    }
+
+    /// Encoder and muxer taking raw audio and producing the file contents.
+    fn encoder(&self) -> &'static str {
+        match self {
+            AudioFormat::Opus => {
+                "audioconvert ! audioresample ! audio/x-raw,rate=48000 ! \
+                 opusenc bitrate=128000 ! oggmux"
+            }
+            AudioFormat::Flac => "audioconvert ! flacenc",
+            AudioFormat::Wav => "audioconvert ! wavenc",
+            AudioFormat::M4a => {
+                "audioconvert ! audioresample ! audio/x-raw,rate=44100,channels=2 ! \
+                 avenc_aac bitrate=256000 ! mp4mux"
+            }
+        }
+    }
+}
+
+/// Full pipeline writing the raw audio branches to `location`, mixed into a
+/// single track since most of these formats only hold one.
+pub fn pipeline(branches: &[(&str, String)], format: AudioFormat, location: &str) -> String {
+    let sink = format!("{} ! filesink location={}", format.encoder(), location);
+
+    match branches {
+        [(_, branch)] => format!("{} ! {}", branch, sink),
+        branches => std::iter::once(format!("audiomixer name=amix ! {}", sink))
+            .chain(
+                branches
+                    .iter()
+                    .map(|(_, branch)| format!("{} ! queue ! amix.", branch)),
+            )
+            .collect::<Vec<_>>()
+            .join(" "),
+    }
+}
@@ -0,0 +1,118 @@
+/// How microphone and desktop audio end up in the recording when both are captured.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AudioTrackLayout {
+    /// A single track with both sources mixed together.
+    Mixed,
+    /// One track per source, for editing later.
+    Separate,
+    /// A mixed track followed by one stem per source.
+    Both,
+}
+
+impl AudioTrackLayout {
+    pub const ALL: [AudioTrackLayout; 3] = [
+        AudioTrackLayout::Mixed,
+        AudioTrackLayout::Separate,
+        AudioTrackLayout::Both,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            AudioTrackLayout::Mixed => "Mixed",
+            AudioTrackLayout::Separate => "Separate tracks",
+            AudioTrackLayout::Both => "Mixed + stems",
+        }
+    }
+}
+
+/// Two or three lowercase letters, as ISO 639 language codes are. Anything
+/// else would end up unescaped in the `taginject` tag string.
+pub fn is_language_code(code: &str) -> bool {
+    (2..=3).contains(&code.len()) && code.bytes().all(|b| b.is_ascii_lowercase())
+}
+
+/// Encode raw audio and hand it to the muxer named `mux`, tagged with a track title.
+fn encoder(title: &str, language: &str) -> String {
+    format!(
+        "audioconvert ! audioresample ! audio/x-raw,rate=44100,channels=2 ! \
+         avenc_aac bitrate=320000 ! \
+         taginject tags=\"title=\\\"{}\\\",language-code={}\" ! queue ! mux.",
+        title, language
+    )
+}
+
+/// Pipeline fragment writing the given raw audio branches as tracks of `mux`.
+/// Branches are `(title, fragment)` pairs whose fragments end in raw audio.
+pub fn tracks(branches: &[(&str, String)], layout: AudioTrackLayout, language: &str) -> String {
+    let language = if is_language_code(language.trim()) {
+        language.trim()
+    } else {
+        "und"
+    };
+
+    // A single source is always written as its own track
+    if branches.len() < 2 {
+        return branches
+            .iter()
+            .map(|(title, branch)| format!("{} ! {}", branch, encoder(title, language)))
+            .collect::<Vec<_>>()
+            .join(" ");
+    }
+
+    let mixer = format!("audiomixer name=amix ! {}", encoder("Mixed", language));
+
+    match layout {
+        AudioTrackLayout::Mixed => std::iter::once(mixer)
+            .chain(
+                branches
+                    .iter()
+                    .map(|(_, branch)| format!("{} ! queue ! amix.", branch)),
+            )
+            .collect::<Vec<_>>()
+            .join(" "),
+        AudioTrackLayout::Separate => branches
+            .iter()
+            .map(|(title, branch)| format!("{} ! {}", branch, encoder(title, language)))
+            .collect::<Vec<_>>()
+            .join(" "),
+        AudioTrackLayout::Both => std::iter::once(mixer)
+            .chain(branches.iter().enumerate().map(|(idx, (title, branch))| {
+                format!(
+                    "{branch} ! tee name=stem_{idx} \
+                     stem_{idx}. ! queue ! amix. \
+                     stem_{idx}. ! queue ! {encoder}",
+                    branch = branch,
+                    idx = idx,
+                    encoder = encoder(title, language)
+                )
+            }))
+            .collect::<Vec<_>>()
+            .join(" "),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn accepts_only_iso_639_codes() {
+        for code in ["en", "de", "eng", "und"] {
+            assert!(is_language_code(code), "{}", code);
+        }
+        for code in ["", "e", "engl", "EN", "e n", "en,", "e\"", "en!"] {
+            assert!(!is_language_code(code), "{}", code);
+        }
+    }
+
+    #[test]
+    fn tags_invalid_languages_as_undetermined() {
+        let branches = [("Microphone", "fakesrc".to_string())];
+        let injected = tracks(&branches, AudioTrackLayout::Mixed, "en\" ! fakesink");
+        assert!(injected.contains("language-code=und"));
+        assert!(!injected.contains("fakesink"));
+
+        let french = tracks(&branches, AudioTrackLayout::Mixed, " fr ");
+        assert!(french.contains("language-code=fr\""));
+    }
+}
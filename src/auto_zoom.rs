@@ -0,0 +1,476 @@
+use crate::cursor_fx;
+use crate::keystrokes;
+use crate::video_transform::VideoTransform;
+use core_graphics::geometry::CGRect;
+use eframe::egui;
+use gstreamer as gst;
+use gstreamer::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// How often the pointer is sampled and the zoom stepped, about once a frame.
+const STEP_INTERVAL: Duration = Duration::from_millis(16);
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum ZoomFollow {
+    /// Stay zoomed in and track the pointer.
+    Pointer,
+    /// Zoom in on each click, then back out once things are quiet.
+    Clicks,
+}
+
+impl ZoomFollow {
+    pub const ALL: [ZoomFollow; 2] = [ZoomFollow::Pointer, ZoomFollow::Clicks];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            ZoomFollow::Pointer => "Pointer",
+            ZoomFollow::Clicks => "Clicks",
+        }
+    }
+}
+
+/// Modifier keys that zoom in while held anywhere, even with another app in front.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum ZoomHold {
+    Off,
+    ControlShift,
+    /// Also the VoiceOver modifier.
+    ControlOption,
+    CommandShift,
+}
+
+impl ZoomHold {
+    pub const ALL: [ZoomHold; 4] = [
+        ZoomHold::Off,
+        ZoomHold::ControlShift,
+        ZoomHold::ControlOption,
+        ZoomHold::CommandShift,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            ZoomHold::Off => "Off",
+            ZoomHold::ControlShift => "Ctrl+Shift",
+            ZoomHold::ControlOption => "Ctrl+Option",
+            ZoomHold::CommandShift => "Cmd+Shift",
+        }
+    }
+
+    pub fn is_held(&self, modifiers: egui::Modifiers) -> bool {
+        match self {
+            ZoomHold::Off => false,
+            ZoomHold::ControlShift => modifiers.ctrl && modifiers.shift,
+            ZoomHold::ControlOption => modifiers.ctrl && modifiers.alt,
+            ZoomHold::CommandShift => modifiers.command && modifiers.shift,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ZoomSettings {
+    pub enabled: bool,
+    pub follow: ZoomFollow,
+    /// Magnification when zoomed in, 2.0 shows a quarter of the frame.
+    pub level: f32,
+    /// Seconds the view takes to mostly catch up with its target.
+    pub easing: f32,
+    /// Share of the visible area around its centre the pointer can move in
+    /// without panning.
+    pub dead_zone: f32,
+    /// Seconds to stay zoomed in after a click when following clicks.
+    pub click_hold: f32,
+    pub hold: ZoomHold,
+}
+
+impl Default for ZoomSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            follow: ZoomFollow::Pointer,
+            level: 2.0,
+            easing: 0.3,
+            dead_zone: 0.3,
+            click_hold: 2.0,
+            hold: ZoomHold::ControlShift,
+        }
+    }
+}
+
+/// Animated view into the main video, pushed into the recording's
+/// `compositor name=zoom` stage.
+pub struct AutoZoom {
+    /// Centre of the view as a fraction of the frame.
+    center: [f32; 2],
+    zoom: f32,
+    last_update: Instant,
+    last_click: Option<([f32; 2], Instant)>,
+    pressed: bool,
+}
+
+impl Default for AutoZoom {
+    fn default() -> Self {
+        Self {
+            center: [0.5, 0.5],
+            zoom: 1.0,
+            last_update: Instant::now(),
+            last_click: None,
+            pressed: false,
+        }
+    }
+}
+
+impl AutoZoom {
+    /// Move the view one step towards its target. `pointer` is the pointer as a
+    /// fraction of the frame with the left button state, when it is over the
+    /// captured source. `hold` zooms in regardless of the settings.
+    pub fn update(
+        &mut self,
+        settings: &ZoomSettings,
+        pointer: Option<([f32; 2], bool)>,
+        hold: bool,
+    ) {
+        let now = Instant::now();
+        let dt = now.duration_since(self.last_update).as_secs_f32();
+        self.last_update = now;
+
+        let pressed = pointer.is_some_and(|(_, down)| down);
+        if let Some((at, true)) = pointer.filter(|_| !self.pressed) {
+            self.last_click = Some((at, now));
+        }
+        self.pressed = pressed;
+        let recent_click = self.last_click.filter(|(_, at)| {
+            now.duration_since(*at) < Duration::from_secs_f32(settings.click_hold)
+        });
+
+        // What to look at, and how closely
+        let (focus, zoomed) = match settings.follow {
+            _ if !settings.enabled => (pointer.map(|(at, _)| at), false),
+            ZoomFollow::Pointer => (pointer.map(|(at, _)| at), true),
+            ZoomFollow::Clicks => match recent_click {
+                Some((at, _)) => (Some(at), true),
+                None => (pointer.map(|(at, _)| at), false),
+            },
+        };
+        let target_zoom = if zoomed || hold {
+            settings.level.max(1.0)
+        } else {
+            1.0
+        };
+
+        // Only pan once the focus leaves the dead zone, and then just enough
+        // to bring it back to the zone's edge
+        let half = 0.5 / self.zoom;
+        let mut target = self.center;
+        if let Some(focus) = focus {
+            let slack = half * settings.dead_zone.clamp(0.0, 0.95);
+            for ((target, focus), center) in target.iter_mut().zip(focus).zip(self.center) {
+                let offset = focus - center;
+                if offset.abs() > slack {
+                    *target = focus - slack * offset.signum();
+                }
+            }
+        }
+
+        // Exponential easing, framerate independent
+        let t = 1.0 - (-dt / settings.easing.max(0.01) * 3.0).exp();
+        self.zoom += (target_zoom - self.zoom) * t;
+
+        // Keep the view inside the frame
+        let half = 0.5 / self.zoom;
+        for (center, target) in self.center.iter_mut().zip(target) {
+            *center = (*center + (target - *center) * t).clamp(half, 1.0 - half);
+        }
+    }
+
+    pub fn is_zoomed(&self) -> bool {
+        self.zoom > 1.01
+    }
+
+    /// The visible part of the frame as left, top, right and bottom fractions.
+    pub fn view(&self) -> [f32; 4] {
+        let half = 0.5 / self.zoom;
+        [
+            self.center[0] - half,
+            self.center[1] - half,
+            self.center[0] + half,
+            self.center[1] + half,
+        ]
+    }
+
+    /// Where to place the whole frame on the `zoom` compositor's pad, as x, y,
+    /// width and height, so the view fills its `width` by `height` output.
+    pub fn placement(&self, width: i32, height: i32) -> [i32; 4] {
+        let [left, top, ..] = self.view();
+        let width = width as f32 * self.zoom;
+        let height = height as f32 * self.zoom;
+        [-left * width, -top * height, width, height].map(|v| v.round() as i32)
+    }
+}
+
+#[derive(Default)]
+struct Followed {
+    settings: ZoomSettings,
+    zoom: AutoZoom,
+    /// The captured display in global points, and how its video is flipped
+    /// and rotated. None when the main source isn't a screen.
+    display: Option<(CGRect, VideoTransform)>,
+    /// The recording's zoom pad and the size of the frame it fills.
+    recording: Option<(gst::Pad, i32, i32)>,
+}
+
+impl Followed {
+    fn step(&mut self) {
+        // The pointer as a fraction of the captured display, if it is on it,
+        // then through the same flip and rotation as the video
+        let pointer = self.display.as_ref().and_then(|(bounds, transform)| {
+            let (position, buttons) = cursor_fx::pointer_state()?;
+            let x = (position[0] - bounds.origin.x) / bounds.size.width;
+            let y = (position[1] - bounds.origin.y) / bounds.size.height;
+            ((0.0..=1.0).contains(&x) && (0.0..=1.0).contains(&y))
+                .then(|| (transform.orient([x as f32, y as f32]), buttons[0]))
+        });
+        let hold = self.settings.hold.is_held(keystrokes::held_modifiers());
+        self.zoom.update(&self.settings, pointer, hold);
+
+        let Some((pad, width, height)) = &self.recording else {
+            return;
+        };
+        let placement = self.zoom.placement(*width, *height);
+        for (name, value) in ["xpos", "ypos", "width", "height"]
+            .into_iter()
+            .zip(placement)
+        {
+            if pad.property::<i32>(name) != value {
+                pad.set_property(name, value);
+            }
+        }
+    }
+}
+
+/// Follows the pointer on a thread of its own and pushes the view into the
+/// recording from there, so the zoom keeps up while the window is hidden and
+/// not repainting. The thread stops once this is dropped.
+pub struct ZoomWatcher {
+    followed: Arc<Mutex<Followed>>,
+}
+
+impl ZoomWatcher {
+    pub fn spawn(settings: ZoomSettings) -> Self {
+        let followed = Arc::new(Mutex::new(Followed {
+            settings,
+            ..Followed::default()
+        }));
+        let weak = Arc::downgrade(&followed);
+        std::thread::spawn(move || {
+            while let Some(followed) = weak.upgrade() {
+                followed.lock().unwrap().step();
+                drop(followed);
+                std::thread::sleep(STEP_INTERVAL);
+            }
+        });
+        Self { followed }
+    }
+
+    pub fn set_settings(&self, settings: &ZoomSettings) {
+        self.followed.lock().unwrap().settings = settings.clone();
+    }
+
+    /// Follow the pointer over `display`, or nothing when the main source
+    /// isn't a screen.
+    pub fn set_display(&self, display: Option<(CGRect, VideoTransform)>) {
+        self.followed.lock().unwrap().display = display;
+    }
+
+    /// Zoom the recording's `zoom` stage, which fills a `width` by `height` frame.
+    pub fn set_recording(&self, pipeline: &gst::Pipeline, width: i32, height: i32) {
+        let pad = pipeline
+            .by_name("zoom")
+            .and_then(|zoom| zoom.static_pad("sink_0"));
+        self.followed.lock().unwrap().recording = pad.map(|pad| (pad, width, height));
+    }
+
+    pub fn clear_recording(&self) {
+        self.followed.lock().unwrap().recording = None;
+    }
+
+    /// The visible part of the frame while zoomed in, see [`AutoZoom::view`].
+    pub fn view(&self) -> Option<[f32; 4]> {
+        let followed = self.followed.lock().unwrap();
+        followed.zoom.is_zoomed().then(|| followed.zoom.view())
+    }
+}
+
+/// Controls for the auto zoom, returns true when anything changed.
+pub fn controls(ui: &mut egui::Ui, settings: &mut ZoomSettings) -> bool {
+    let mut changed = false;
+    ui.horizontal(|ui| {
+        changed |= ui.checkbox(&mut settings.enabled, "Follow").changed();
+        ui.add_enabled_ui(settings.enabled, |ui| {
+            egui::ComboBox::from_id_salt("zoom_follow")
+                .selected_text(settings.follow.label())
+                .show_ui(ui, |ui| {
+                    for follow in ZoomFollow::ALL {
+                        changed |= ui
+                            .selectable_value(&mut settings.follow, follow, follow.label())
+                            .changed();
+                    }
+                });
+        });
+    });
+    changed |= ui
+        .add(
+            egui::Slider::new(&mut settings.level, 1.25..=4.0)
+                .suffix("x")
+                .text("Zoom"),
+        )
+        .changed();
+    changed |= ui
+        .add(
+            egui::Slider::new(&mut settings.easing, 0.05..=1.5)
+                .suffix(" s")
+                .text("Easing"),
+        )
+        .changed();
+    changed |= ui
+        .add(egui::Slider::new(&mut settings.dead_zone, 0.0..=0.9).text("Dead zone"))
+        .changed();
+    if settings.follow == ZoomFollow::Clicks {
+        changed |= ui
+            .add(
+                egui::Slider::new(&mut settings.click_hold, 0.5..=10.0)
+                    .suffix(" s")
+                    .text("Stay after click"),
+            )
+            .changed();
+    }
+    ui.horizontal(|ui| {
+        ui.label("Hold to zoom");
+        egui::ComboBox::from_id_salt("zoom_hold")
+            .selected_text(settings.hold.label())
+            .show_ui(ui, |ui| {
+                for hold in ZoomHold::ALL {
+                    changed |= ui
+                        .selectable_value(&mut settings.hold, hold, hold.label())
+                        .changed();
+                }
+            });
+    });
+    changed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn following(follow: ZoomFollow) -> ZoomSettings {
+        ZoomSettings {
+            enabled: true,
+            follow,
+            ..ZoomSettings::default()
+        }
+    }
+
+    /// Step long enough after the last one for the easing to fully catch up.
+    fn settle(zoom: &mut AutoZoom, settings: &ZoomSettings, pointer: Option<([f32; 2], bool)>) {
+        zoom.last_update = Instant::now() - Duration::from_secs(10);
+        zoom.update(settings, pointer, false);
+    }
+
+    #[test]
+    fn stays_out_until_asked() {
+        let mut zoom = AutoZoom::default();
+        settle(
+            &mut zoom,
+            &ZoomSettings::default(),
+            Some(([0.1, 0.1], true)),
+        );
+        assert!(!zoom.is_zoomed());
+        assert_eq!(zoom.placement(1920, 1080), [0, 0, 1920, 1080]);
+
+        zoom.last_update = Instant::now() - Duration::from_secs(10);
+        zoom.update(&ZoomSettings::default(), None, true);
+        assert!(zoom.is_zoomed());
+    }
+
+    #[test]
+    fn keeps_the_view_inside_the_frame() {
+        let mut zoom = AutoZoom::default();
+        settle(
+            &mut zoom,
+            &following(ZoomFollow::Pointer),
+            Some(([0.0, 0.0], false)),
+        );
+        assert_eq!(zoom.view(), [0.0, 0.0, 0.5, 0.5]);
+        assert_eq!(zoom.placement(1920, 1080), [0, 0, 3840, 2160]);
+
+        settle(
+            &mut zoom,
+            &following(ZoomFollow::Pointer),
+            Some(([1.0, 1.0], false)),
+        );
+        assert_eq!(zoom.view(), [0.5, 0.5, 1.0, 1.0]);
+        assert_eq!(zoom.placement(1920, 1080), [-1920, -1080, 3840, 2160]);
+    }
+
+    #[test]
+    fn pans_only_out_of_the_dead_zone() {
+        let settings = following(ZoomFollow::Pointer);
+        let mut zoom = AutoZoom::default();
+        settle(&mut zoom, &settings, Some(([0.5, 0.5], false)));
+
+        // The zone spans 0.3 of the visible half width around the centre
+        settle(&mut zoom, &settings, Some(([0.55, 0.5], false)));
+        assert_eq!(zoom.view(), [0.25, 0.25, 0.75, 0.75]);
+
+        // Just far enough to bring the pointer back to the zone's edge
+        settle(&mut zoom, &settings, Some(([0.7, 0.5], false)));
+        assert_eq!(zoom.placement(1920, 1080), [-1440, -540, 3840, 2160]);
+    }
+
+    #[test]
+    fn eases_towards_the_target() {
+        let settings = following(ZoomFollow::Pointer);
+        let mut zoom = AutoZoom {
+            last_update: Instant::now() - Duration::from_millis(100),
+            ..AutoZoom::default()
+        };
+        zoom.update(&settings, Some(([0.5, 0.5], false)), false);
+
+        // A tenth of a second is a third of the 0.3 s easing, 1 - e^-1 of the way
+        let expected = 1.0 + (1.0 - (-1.0f32).exp());
+        assert!((zoom.zoom - expected).abs() < 0.01, "zoom {}", zoom.zoom);
+    }
+
+    #[test]
+    fn zooms_on_clicks_until_the_hold_runs_out() {
+        let settings = following(ZoomFollow::Clicks);
+        let mut zoom = AutoZoom::default();
+        settle(&mut zoom, &settings, Some(([0.2, 0.2], false)));
+        assert!(!zoom.is_zoomed());
+
+        settle(&mut zoom, &settings, Some(([0.2, 0.2], true)));
+        assert!(zoom.is_zoomed());
+        settle(&mut zoom, &settings, Some(([0.2, 0.2], false)));
+        assert!(zoom.is_zoomed());
+
+        let (at, _) = zoom.last_click.unwrap();
+        zoom.last_click = Some((at, Instant::now() - Duration::from_secs(3)));
+        settle(&mut zoom, &settings, Some(([0.2, 0.2], false)));
+        assert!(!zoom.is_zoomed());
+    }
+
+    #[test]
+    fn holds_only_the_chosen_chord() {
+        let ctrl_shift = egui::Modifiers {
+            ctrl: true,
+            shift: true,
+            ..Default::default()
+        };
+        assert!(ZoomHold::ControlShift.is_held(ctrl_shift));
+        assert!(!ZoomHold::ControlOption.is_held(ctrl_shift));
+        assert!(!ZoomHold::Off.is_held(ctrl_shift));
+    }
+}
@@ -0,0 +1,158 @@
+use eframe::egui;
+use gstreamer as gst;
+use gstreamer::prelude::*;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum KeyColor {
+    Green,
+    Blue,
+    Custom,
+}
+
+impl KeyColor {
+    pub const ALL: [KeyColor; 3] = [KeyColor::Green, KeyColor::Blue, KeyColor::Custom];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            KeyColor::Green => "Green screen",
+            KeyColor::Blue => "Blue screen",
+            KeyColor::Custom => "Custom color",
+        }
+    }
+
+    /// The `alpha` element's method for this key.
+    fn method(&self) -> &'static str {
+        match self {
+            KeyColor::Green => "green",
+            KeyColor::Blue => "blue",
+            KeyColor::Custom => "custom",
+        }
+    }
+}
+
+/// Background removal for the PiP camera. The PiP pipeline keys its RGBA frames
+/// with `alpha name=chroma`, which just sets full opacity while disabled.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChromaKey {
+    pub enabled: bool,
+    pub color: KeyColor,
+    pub custom_color: [u8; 3],
+    /// Hue angle around the key color that becomes transparent, in degrees.
+    pub tolerance: f32,
+    /// Softens the edge between keyed and kept pixels.
+    pub noise_level: f32,
+    /// How much of the key color's tint is removed from the kept pixels, 0 to 1.
+    pub spill: f32,
+}
+
+impl Default for ChromaKey {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            color: KeyColor::Green,
+            custom_color: [0, 255, 0],
+            tolerance: 20.0,
+            noise_level: 2.0,
+            spill: 0.5,
+        }
+    }
+}
+
+impl ChromaKey {
+    pub fn apply(&self, pipeline: &gst::Pipeline) {
+        let Some(alpha) = pipeline.by_name("chroma") else {
+            return;
+        };
+
+        if !self.enabled {
+            alpha.set_property_from_str("method", "set");
+            alpha.set_property("alpha", 1.0f64);
+            return;
+        }
+
+        let [r, g, b] = match self.color {
+            KeyColor::Green => [0, 255, 0],
+            KeyColor::Blue => [0, 0, 255],
+            KeyColor::Custom => self.custom_color,
+        };
+        alpha.set_property_from_str("method", self.color.method());
+        alpha.set_property("target-r", r as u32);
+        alpha.set_property("target-g", g as u32);
+        alpha.set_property("target-b", b as u32);
+        alpha.set_property("angle", self.tolerance);
+        alpha.set_property("noise-level", self.noise_level);
+    }
+
+    /// Index of the RGB channel the key color is made of.
+    fn key_channel(&self) -> usize {
+        match self.color {
+            KeyColor::Green => 1,
+            KeyColor::Blue => 2,
+            KeyColor::Custom => {
+                let [r, g, b] = self.custom_color;
+                if g >= r && g >= b {
+                    1
+                } else if b >= r {
+                    2
+                } else {
+                    0
+                }
+            }
+        }
+    }
+}
+
+/// Pull the key channel of an RGBA frame down towards the other two, removing
+/// the colored fringe the screen reflects onto hair and edges.
+pub fn suppress_spill(data: &mut [u8], key: &ChromaKey) {
+    if !key.enabled || key.spill <= 0.0 {
+        return;
+    }
+
+    let channel = key.key_channel();
+    let others = [(channel + 1) % 3, (channel + 2) % 3];
+    for pixel in data.chunks_exact_mut(4) {
+        let limit = (pixel[others[0]] as f32 + pixel[others[1]] as f32) / 2.0;
+        let value = pixel[channel] as f32;
+        if value > limit {
+            pixel[channel] = (value - (value - limit) * key.spill).round() as u8;
+        }
+    }
+}
+
+/// Key controls, returns true when anything changed.
+pub fn controls(ui: &mut egui::Ui, key: &mut ChromaKey) -> bool {
+    let mut changed = ui.checkbox(&mut key.enabled, "Remove background").changed();
+    if !key.enabled {
+        return changed;
+    }
+
+    ui.horizontal(|ui| {
+        egui::ComboBox::from_id_salt("chroma_key_color")
+            .selected_text(key.color.label())
+            .show_ui(ui, |ui| {
+                for color in KeyColor::ALL {
+                    changed |= ui
+                        .selectable_value(&mut key.color, color, color.label())
+                        .changed();
+                }
+            });
+        if key.color == KeyColor::Custom {
+            changed |= ui.color_edit_button_srgb(&mut key.custom_color).changed();
+        }
+    });
+    changed |= ui
+        .add(
+            egui::Slider::new(&mut key.tolerance, 1.0..=90.0)
+                .suffix("°")
+                .text("Tolerance"),
+        )
+        .changed();
+    changed |= ui
+        .add(egui::Slider::new(&mut key.noise_level, 0.0..=64.0).text("Edge softness"))
+        .changed();
+    changed |= ui
+        .add(egui::Slider::new(&mut key.spill, 0.0..=1.0).text("Spill"))
+        .changed();
+    changed
+}
@@ -0,0 +1,110 @@
+use eframe::egui;
+
+/// Pixels on each side of the sampled one shown in the loupe.
+const LOUPE_RADIUS: i32 = 5;
+const LOUPE_CELL: f32 = 12.0;
+
+pub fn hex(pixel: [u8; 4]) -> String {
+    format!("#{:02X}{:02X}{:02X}", pixel[0], pixel[1], pixel[2])
+}
+
+/// Hue in degrees, saturation and lightness in percent.
+pub fn hsl(pixel: [u8; 4]) -> (f32, f32, f32) {
+    let [r, g, b] = [pixel[0], pixel[1], pixel[2]].map(|c| c as f32 / 255.0);
+    let max = r.max(g).max(b);
+    let min = r.min(g).min(b);
+    let lightness = (max + min) / 2.0;
+    let delta = max - min;
+
+    if delta == 0.0 {
+        return (0.0, 0.0, lightness * 100.0);
+    }
+
+    let saturation = delta / (1.0 - (2.0 * lightness - 1.0).abs());
+    let hue = if max == r {
+        60.0 * ((g - b) / delta).rem_euclid(6.0)
+    } else if max == g {
+        60.0 * ((b - r) / delta + 2.0)
+    } else {
+        60.0 * ((r - g) / delta + 4.0)
+    };
+
+    (hue, saturation * 100.0, lightness * 100.0)
+}
+
+/// Zoomed view of the pixels around `source` (in frame coordinates), drawn next
+/// to the pointer at `pointer`, followed by the sampled pixel's values.
+pub fn show_loupe(
+    ctx: &egui::Context,
+    pointer: egui::Pos2,
+    source: (i32, i32),
+    get_pixel: impl Fn(i32, i32) -> Option<[u8; 4]>,
+) {
+    let Some(center) = get_pixel(source.0, source.1) else {
+        return;
+    };
+
+    egui::Area::new(egui::Id::new("color_picker_loupe"))
+        .order(egui::Order::Tooltip)
+        .fixed_pos(pointer + egui::vec2(20.0, 20.0))
+        .interactable(false)
+        .show(ctx, |ui| {
+            egui::Frame::popup(ui.style()).show(ui, |ui| {
+                let cells = (LOUPE_RADIUS * 2 + 1) as f32;
+                let (rect, _) = ui.allocate_exact_size(
+                    egui::vec2(cells * LOUPE_CELL, cells * LOUPE_CELL),
+                    egui::Sense::hover(),
+                );
+                let painter = ui.painter_at(rect);
+
+                for dy in -LOUPE_RADIUS..=LOUPE_RADIUS {
+                    for dx in -LOUPE_RADIUS..=LOUPE_RADIUS {
+                        let color = get_pixel(source.0 + dx, source.1 + dy)
+                            .map(|[r, g, b, _]| egui::Color32::from_rgb(r, g, b))
+                            .unwrap_or(egui::Color32::BLACK);
+                        let min = rect.min
+                            + egui::vec2(
+                                (dx + LOUPE_RADIUS) as f32 * LOUPE_CELL,
+                                (dy + LOUPE_RADIUS) as f32 * LOUPE_CELL,
+                            );
+                        painter.rect_filled(
+                            egui::Rect::from_min_size(min, egui::Vec2::splat(LOUPE_CELL)),
+                            0.0,
+                            color,
+                        );
+                    }
+                }
+
+                // Outline the sampled pixel
+                let sampled = egui::Rect::from_min_size(
+                    rect.min + egui::Vec2::splat(LOUPE_RADIUS as f32 * LOUPE_CELL),
+                    egui::Vec2::splat(LOUPE_CELL),
+                );
+                painter.rect_stroke(sampled, 0.0, egui::Stroke::new(2.0, egui::Color32::WHITE));
+
+                let (h, s, l) = hsl(center);
+                ui.add_space(4.0);
+                ui.label(egui::RichText::new(hex(center)).monospace().strong());
+                ui.label(
+                    egui::RichText::new(format!(
+                        "RGBA {} {} {} {}",
+                        center[0], center[1], center[2], center[3]
+                    ))
+                    .monospace(),
+                );
+                ui.label(
+                    egui::RichText::new(format!("HSL {:.0}° {:.0}% {:.0}%", h, s, l)).monospace(),
+                );
+                ui.label(
+                    egui::RichText::new(format!("x {} y {}", source.0, source.1))
+                        .monospace()
+                        .color(egui::Color32::GRAY),
+                );
+                ui.label(
+                    egui::RichText::new("Click to copy")
+                        .size(11.0)
+                        .color(egui::Color32::GRAY),
+                );
+            });
+        });
+}
@@ -0,0 +1,182 @@
+use crate::raster::Canvas;
+use crate::video_transform::VideoTransform;
+use core_graphics::event::{CGEvent, CGMouseButton};
+use core_graphics::event_source::{CGEventSource, CGEventSourceStateID};
+use core_graphics::geometry::CGRect;
+use eframe::egui;
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, Instant};
+
+const RIPPLE_DURATION: Duration = Duration::from_millis(500);
+
+#[link(name = "CoreGraphics", kind = "framework")]
+extern "C" {
+    fn CGEventSourceButtonState(state_id: CGEventSourceStateID, button: CGMouseButton) -> bool;
+}
+
+/// Pointer position in global display points and the left, right and middle button state.
+pub fn pointer_state() -> Option<([f64; 2], [bool; 3])> {
+    let source = CGEventSource::new(CGEventSourceStateID::CombinedSessionState).ok()?;
+    let location = CGEvent::new(source).ok()?.location();
+    let buttons = [
+        CGMouseButton::Left,
+        CGMouseButton::Right,
+        CGMouseButton::Center,
+    ]
+    .map(|button| unsafe {
+        CGEventSourceButtonState(CGEventSourceStateID::CombinedSessionState, button)
+    });
+    Some(([location.x, location.y], buttons))
+}
+
+/// Pointer highlight and click ripples drawn into screen recordings.
+/// Sizes are in display points, so they look the same on Retina displays.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CursorEffects {
+    pub halo: bool,
+    pub halo_color: [u8; 4],
+    pub halo_radius: f32,
+    pub ripples: bool,
+    pub ripple_color: [u8; 4],
+    pub ripple_radius: f32,
+    /// Which of the left, right and middle buttons start a ripple.
+    pub ripple_buttons: [bool; 3],
+}
+
+impl Default for CursorEffects {
+    fn default() -> Self {
+        Self {
+            halo: false,
+            halo_color: [255, 220, 0, 90],
+            halo_radius: 28.0,
+            ripples: false,
+            ripple_color: [255, 80, 80, 220],
+            ripple_radius: 36.0,
+            ripple_buttons: [true, true, false],
+        }
+    }
+}
+
+impl CursorEffects {
+    pub fn is_enabled(&self) -> bool {
+        self.halo || self.ripples
+    }
+}
+
+/// Draws `CursorEffects` over frames captured from one display.
+pub struct CursorLayer {
+    pub effects: CursorEffects,
+    /// The captured display in global points.
+    pub display: CGRect,
+    /// Orientation of the captured frames, so effects land where the pointer is.
+    pub transform: VideoTransform,
+    pressed: [bool; 3],
+    /// Click positions in display points and when they happened.
+    ripples: Vec<([f64; 2], Instant)>,
+}
+
+impl CursorLayer {
+    pub fn new(effects: CursorEffects, display: CGRect, transform: VideoTransform) -> Self {
+        Self {
+            effects,
+            display,
+            transform,
+            pressed: [false; 3],
+            ripples: Vec::new(),
+        }
+    }
+
+    pub fn draw(&mut self, canvas: &mut Canvas, width: usize, height: usize) {
+        let Some((pointer, buttons)) = pointer_state() else {
+            return;
+        };
+        let now = Instant::now();
+
+        // A ripple starts on each new press of a selected button
+        for (idx, &down) in buttons.iter().enumerate() {
+            if down
+                && !self.pressed[idx]
+                && self.effects.ripples
+                && self.effects.ripple_buttons[idx]
+            {
+                self.ripples.push((pointer, now));
+            }
+        }
+        self.pressed = buttons;
+        self.ripples
+            .retain(|(_, started)| now.duration_since(*started) < RIPPLE_DURATION);
+
+        // Display points to frame pixels, through the source's flip and rotation
+        let to_frame = |p: [f64; 2]| {
+            let [x, y] = self.transform.orient([
+                ((p[0] - self.display.origin.x) / self.display.size.width) as f32,
+                ((p[1] - self.display.origin.y) / self.display.size.height) as f32,
+            ]);
+            [x * width as f32, y * height as f32]
+        };
+        let frame_width = if self.transform.is_sideways() {
+            height
+        } else {
+            width
+        };
+        let scale = (frame_width as f64 / self.display.size.width) as f32;
+
+        if self.effects.halo {
+            canvas.fill_circle(
+                to_frame(pointer),
+                self.effects.halo_radius * scale,
+                self.effects.halo_color,
+            );
+        }
+
+        for (position, started) in &self.ripples {
+            let t = now.duration_since(*started).as_secs_f32() / RIPPLE_DURATION.as_secs_f32();
+            // Grow quickly, then fade out
+            let radius =
+                self.effects.ripple_radius * scale * (0.2 + 0.8 * (1.0 - (1.0 - t).powi(2)));
+            let mut color = self.effects.ripple_color;
+            color[3] = (color[3] as f32 * (1.0 - t)) as u8;
+            canvas.ring(to_frame(*position), radius, 3.0 * scale, color);
+        }
+    }
+}
+
+fn color_edit(ui: &mut egui::Ui, color: &mut [u8; 4]) -> bool {
+    let [r, g, b, a] = *color;
+    let mut color32 = egui::Color32::from_rgba_unmultiplied(r, g, b, a);
+    let changed = ui.color_edit_button_srgba(&mut color32).changed();
+    if changed {
+        *color = color32.to_srgba_unmultiplied();
+    }
+    changed
+}
+
+/// Halo and ripple controls, returns true when anything changed.
+pub fn controls(ui: &mut egui::Ui, effects: &mut CursorEffects) -> bool {
+    let mut changed = false;
+    ui.horizontal(|ui| {
+        changed |= ui.checkbox(&mut effects.halo, "Highlight").changed();
+        changed |= color_edit(ui, &mut effects.halo_color);
+        changed |= ui
+            .add(egui::Slider::new(&mut effects.halo_radius, 8.0..=80.0).text("Size"))
+            .changed();
+    });
+    ui.horizontal(|ui| {
+        changed |= ui.checkbox(&mut effects.ripples, "Click ripples").changed();
+        changed |= color_edit(ui, &mut effects.ripple_color);
+        changed |= ui
+            .add(egui::Slider::new(&mut effects.ripple_radius, 8.0..=120.0).text("Size"))
+            .changed();
+    });
+    ui.add_enabled_ui(effects.ripples, |ui| {
+        ui.horizontal(|ui| {
+            for (idx, name) in ["Left", "Right", "Middle"].into_iter().enumerate() {
+                changed |= ui
+                    .checkbox(&mut effects.ripple_buttons[idx], name)
+                    .changed();
+            }
+        });
+    });
+    changed
+}
@@ -0,0 +1,30 @@
+use crate::annotations::Annotations;
+use crate::cursor_fx::CursorLayer;
+use crate::privacy_mask::{self, PrivacyMask};
+use crate::raster::Canvas;
+
+/// Everything the main source's frame callback bakes into each frame, so
+/// the preview, recordings and screenshots all show the same thing.
+#[derive(Default)]
+pub struct FrameLayers {
+    /// Masks that are currently on.
+    pub masks: Vec<PrivacyMask>,
+    pub annotations: Annotations,
+    /// Only set while a display is captured with a cursor effect enabled.
+    pub cursor: Option<CursorLayer>,
+}
+
+impl FrameLayers {
+    pub fn draw(&mut self, frame: &mut [u8], width: usize, height: usize) {
+        privacy_mask::apply(frame, width, height, &self.masks);
+
+        // Drawn over the masks, so strokes and the pointer stay visible on hidden regions
+        let Some(mut canvas) = Canvas::new(frame, width, height) else {
+            return;
+        };
+        self.annotations.draw(&mut canvas, width, height);
+        if let Some(cursor) = &mut self.cursor {
+            cursor.draw(&mut canvas, width, height);
+        }
+    }
+}
@@ -0,0 +1,346 @@
+use core_graphics::event_source::CGEventSourceStateID;
+use eframe::egui;
+use gstreamer as gst;
+use gstreamer::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+#[link(name = "CoreGraphics", kind = "framework")]
+extern "C" {
+    fn CGEventSourceKeyState(state_id: CGEventSourceStateID, key: u16) -> bool;
+    fn CGEventSourceFlagsState(state_id: CGEventSourceStateID) -> u64;
+}
+
+// CGEventFlags masks
+const FLAG_SHIFT: u64 = 0x0002_0000;
+const FLAG_CONTROL: u64 = 0x0004_0000;
+const FLAG_OPTION: u64 = 0x0008_0000;
+const FLAG_COMMAND: u64 = 0x0010_0000;
+
+/// Most combos shown in the bubble at once, older ones scroll out.
+const MAX_COMBOS: usize = 4;
+/// Seconds the bubble takes to fade out once it has lingered.
+const FADE_SECS: f32 = 0.4;
+/// How often the watcher thread reads the keyboard, short enough for quick taps.
+const POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+/// macOS virtual key codes with their labels. `true` marks keys that produce
+/// text, which the typing filter hides unless Cmd, Ctrl or Option is held.
+const KEYS: &[(u16, &str, bool)] = &[
+    (0x00, "A", true),
+    (0x0B, "B", true),
+    (0x08, "C", true),
+    (0x02, "D", true),
+    (0x0E, "E", true),
+    (0x03, "F", true),
+    (0x05, "G", true),
+    (0x04, "H", true),
+    (0x22, "I", true),
+    (0x26, "J", true),
+    (0x28, "K", true),
+    (0x25, "L", true),
+    (0x2E, "M", true),
+    (0x2D, "N", true),
+    (0x1F, "O", true),
+    (0x23, "P", true),
+    (0x0C, "Q", true),
+    (0x0F, "R", true),
+    (0x01, "S", true),
+    (0x11, "T", true),
+    (0x20, "U", true),
+    (0x09, "V", true),
+    (0x0D, "W", true),
+    (0x07, "X", true),
+    (0x10, "Y", true),
+    (0x06, "Z", true),
+    (0x1D, "0", true),
+    (0x12, "1", true),
+    (0x13, "2", true),
+    (0x14, "3", true),
+    (0x15, "4", true),
+    (0x17, "5", true),
+    (0x16, "6", true),
+    (0x1A, "7", true),
+    (0x1C, "8", true),
+    (0x19, "9", true),
+    (0x1B, "-", true),
+    (0x18, "=", true),
+    (0x21, "[", true),
+    (0x1E, "]", true),
+    (0x2A, "\\", true),
+    (0x29, ";", true),
+    (0x27, "'", true),
+    (0x2B, ",", true),
+    (0x2F, ".", true),
+    (0x2C, "/", true),
+    (0x32, "`", true),
+    (0x31, "Space", true),
+    (0x24, "Return", true),
+    (0x30, "Tab", true),
+    (0x33, "Delete", true),
+    (0x75, "Fwd Delete", true),
+    (0x35, "Esc", false),
+    (0x7B, "Left", false),
+    (0x7C, "Right", false),
+    (0x7D, "Down", false),
+    (0x7E, "Up", false),
+    (0x73, "Home", false),
+    (0x77, "End", false),
+    (0x74, "Page Up", false),
+    (0x79, "Page Down", false),
+    (0x7A, "F1", false),
+    (0x78, "F2", false),
+    (0x63, "F3", false),
+    (0x76, "F4", false),
+    (0x60, "F5", false),
+    (0x61, "F6", false),
+    (0x62, "F7", false),
+    (0x64, "F8", false),
+    (0x65, "F9", false),
+    (0x6D, "F10", false),
+    (0x67, "F11", false),
+    (0x6F, "F12", false),
+];
+
+/// Modifier keys held anywhere on the system, even while the app is in the background.
+pub fn held_modifiers() -> egui::Modifiers {
+    let flags = unsafe { CGEventSourceFlagsState(CGEventSourceStateID::CombinedSessionState) };
+    let command = flags & FLAG_COMMAND != 0;
+    egui::Modifiers {
+        alt: flags & FLAG_OPTION != 0,
+        ctrl: flags & FLAG_CONTROL != 0,
+        shift: flags & FLAG_SHIFT != 0,
+        mac_cmd: command,
+        command,
+    }
+}
+
+/// The `keys` element for the source pipelines, followed by ` ! `, or nothing
+/// when the pango plugin is missing.
+pub fn chain() -> String {
+    if gst::ElementFactory::find("textoverlay").is_some() {
+        "textoverlay name=keys silent=true halignment=center valignment=bottom ypad=48 \
+         shaded-background=true ! "
+            .to_string()
+    } else {
+        String::new()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct KeystrokeSettings {
+    pub enabled: bool,
+    /// Hide keys that type text unless a Cmd, Ctrl or Option combo is held.
+    pub hide_typing: bool,
+    /// Seconds the bubble stays up after the last combo.
+    pub linger: f32,
+    pub font_size: u32,
+}
+
+impl Default for KeystrokeSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            hide_typing: true,
+            linger: 1.5,
+            font_size: 28,
+        }
+    }
+}
+
+/// Recent combos shown in the source pipelines' `textoverlay name=keys`, so
+/// they end up in the preview and recordings.
+#[derive(Default)]
+struct KeystrokeOverlay {
+    /// Key codes that were down at the last poll.
+    down: Vec<u16>,
+    combos: Vec<String>,
+    last_pressed: Option<Instant>,
+    /// Text and alpha last pushed to the element, to skip redundant updates.
+    shown: Option<(String, u32)>,
+}
+
+impl KeystrokeOverlay {
+    /// Read the global key state and record combos for newly pressed keys.
+    fn poll(&mut self, settings: &KeystrokeSettings) {
+        if !settings.enabled {
+            self.down.clear();
+            self.combos.clear();
+            return;
+        }
+
+        let state = CGEventSourceStateID::CombinedSessionState;
+        let flags = unsafe { CGEventSourceFlagsState(state) };
+        let down: Vec<u16> = KEYS
+            .iter()
+            .map(|(code, _, _)| *code)
+            .filter(|code| unsafe { CGEventSourceKeyState(state, *code) })
+            .collect();
+
+        for &(code, label, types_text) in KEYS {
+            if !down.contains(&code) || self.down.contains(&code) {
+                continue;
+            }
+            let shortcut = flags & (FLAG_CONTROL | FLAG_OPTION | FLAG_COMMAND) != 0;
+            if settings.hide_typing && types_text && !shortcut {
+                continue;
+            }
+
+            let mut combo = String::new();
+            for (flag, name) in [
+                (FLAG_CONTROL, "Ctrl+"),
+                (FLAG_OPTION, "Option+"),
+                (FLAG_SHIFT, "Shift+"),
+                (FLAG_COMMAND, "Cmd+"),
+            ] {
+                if flags & flag != 0 {
+                    combo.push_str(name);
+                }
+            }
+            combo.push_str(label);
+
+            // A new bubble starts once the old one has faded
+            if !self.is_showing(settings) {
+                self.combos.clear();
+            }
+            self.combos.push(combo);
+            if self.combos.len() > MAX_COMBOS {
+                self.combos.remove(0);
+            }
+            self.last_pressed = Some(Instant::now());
+        }
+        self.down = down;
+    }
+
+    fn is_showing(&self, settings: &KeystrokeSettings) -> bool {
+        self.opacity(settings) > 0.0
+    }
+
+    /// 1 while the bubble lingers, then fading to 0.
+    fn opacity(&self, settings: &KeystrokeSettings) -> f32 {
+        let Some(pressed) = self.last_pressed else {
+            return 0.0;
+        };
+        if self.combos.is_empty() {
+            return 0.0;
+        }
+        let faded = pressed.elapsed().as_secs_f32() - settings.linger;
+        (1.0 - faded.max(0.0) / FADE_SECS).clamp(0.0, 1.0)
+    }
+
+    /// Push the current bubble into `pipeline`'s `keys` element.
+    fn apply(&mut self, pipeline: &gst::Pipeline, settings: &KeystrokeSettings) {
+        let Some(keys) = pipeline.by_name("keys") else {
+            return;
+        };
+
+        let alpha = (self.opacity(settings) * 255.0).round() as u32;
+        let text = self.combos.join("   ");
+        if self.shown.as_ref() == Some(&(text.clone(), alpha)) {
+            return;
+        }
+
+        keys.set_property("silent", alpha == 0);
+        keys.set_property("text", &text);
+        keys.set_property("font-desc", format!("Sans Bold {}", settings.font_size));
+        // White text on the shaded background, both fading together
+        keys.set_property("color", (alpha << 24) | 0x00FF_FFFF);
+        keys.set_property("shading-value", (alpha * 80 / 255).max(1));
+        self.shown = Some((text, alpha));
+    }
+
+    /// Force the next `apply` to update the element, e.g. after a source switch.
+    fn reset_shown(&mut self) {
+        self.shown = None;
+    }
+}
+
+/// What the watcher thread reads on every poll.
+#[derive(Default)]
+struct Watched {
+    settings: KeystrokeSettings,
+    pipeline: Option<gst::Pipeline>,
+    /// Set when the element needs a full update.
+    reset: bool,
+}
+
+/// Polls the keyboard on a thread of its own and pushes the combos into the
+/// watched pipeline from there, so keys are caught even while the window is
+/// hidden and not repainting. The thread stops once this is dropped.
+pub struct KeystrokeWatcher {
+    watched: Arc<Mutex<Watched>>,
+}
+
+impl KeystrokeWatcher {
+    pub fn spawn(settings: KeystrokeSettings) -> Self {
+        let watched = Arc::new(Mutex::new(Watched {
+            settings,
+            ..Watched::default()
+        }));
+        let weak = Arc::downgrade(&watched);
+        std::thread::spawn(move || {
+            let mut overlay = KeystrokeOverlay::default();
+            while let Some(watched) = weak.upgrade() {
+                let (settings, pipeline) = {
+                    let mut watched = watched.lock().unwrap();
+                    if std::mem::take(&mut watched.reset) {
+                        overlay.reset_shown();
+                    }
+                    (watched.settings.clone(), watched.pipeline.clone())
+                };
+                drop(watched);
+
+                overlay.poll(&settings);
+                if let Some(pipeline) = pipeline {
+                    overlay.apply(&pipeline, &settings);
+                }
+                std::thread::sleep(POLL_INTERVAL);
+            }
+        });
+        Self { watched }
+    }
+
+    pub fn set_settings(&self, settings: &KeystrokeSettings) {
+        let mut watched = self.watched.lock().unwrap();
+        watched.settings = settings.clone();
+        watched.reset = true;
+    }
+
+    /// Show the combos in `pipeline` from now on, e.g. after a source switch.
+    pub fn set_pipeline(&self, pipeline: &gst::Pipeline) {
+        let mut watched = self.watched.lock().unwrap();
+        watched.pipeline = Some(pipeline.clone());
+        watched.reset = true;
+    }
+}
+
+/// Controls for the keystroke overlay, returns true when anything changed.
+pub fn controls(ui: &mut egui::Ui, settings: &mut KeystrokeSettings) -> bool {
+    let mut changed = false;
+    changed |= ui
+        .checkbox(&mut settings.enabled, "Show key presses")
+        .changed();
+    ui.add_enabled_ui(settings.enabled, |ui| {
+        changed |= ui
+            .checkbox(&mut settings.hide_typing, "Hide plain typing")
+            .on_hover_text("Only show keys held with Cmd, Ctrl or Option, and non-text keys")
+            .changed();
+        changed |= ui
+            .add(
+                egui::Slider::new(&mut settings.linger, 0.5..=5.0)
+                    .suffix(" s")
+                    .text("Linger"),
+            )
+            .changed();
+        changed |= ui
+            .add(
+                egui::DragValue::new(&mut settings.font_size)
+                    .range(12..=96)
+                    .suffix(" pt"),
+            )
+            .changed();
+    });
+    changed
+}
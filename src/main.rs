@@ -1,10 +1,17 @@
+use audio_meter::AudioLevel;
+use audio_only::AudioFormat;
+use audio_tracks::AudioTrackLayout;
+use auto_zoom::ZoomWatcher;
+use chroma_key::ChromaKey;
 use chrono;
 use core_graphics::display::{CGDisplay, CGDisplayBounds};
+use cursor_fx::CursorLayer;
 use eframe::egui;
 use egui::epaint::text::layout;
 use egui::FontId;
 use egui::Pos2;
 use egui::ViewportBuilder;
+use frame_layers::FrameLayers;
 use gstreamer as gst;
 use gstreamer::glib;
 use gstreamer::prelude::Cast;
@@ -13,13 +20,51 @@ use gstreamer::DeviceMonitor;
 use gstreamer::{prelude::*, DeviceMonitorFilterId};
 use gstreamer_app;
 use gstreamer_audio;
+use keystrokes::KeystrokeWatcher;
+use mic::{Denoiser, MicSettings};
+use pip_keyframes::{AnimationTarget, PipAnimator, PipKeyframe, TimelineView};
+use pip_layout::PipAnchor;
+use pip_style::{PipShape, PipStyle, StyledFrame};
+use privacy_mask::{MaskEffect, PrivacyMask};
+use recording_state::{format_duration, RecordingEvent, RecordingState, RecordingTimer};
+use scenes::{Crossfade, Scene, SceneAction, SceneTransition};
+use screenshot::{AnnotateView, ScreenshotFormat};
+use settings::Settings;
+use split_layout::BackgroundImage;
 use std::sync::{mpsc, Arc, Mutex};
 use sysinfo::System;
+use system_audio::SystemAudioSettings;
 use tracing::debug;
 
+mod annotations;
+mod audio_meter;
+mod audio_only;
+mod audio_tracks;
+mod auto_zoom;
+mod chroma_key;
+mod color_picker;
+mod cursor_fx;
+mod frame_layers;
+mod keystrokes;
+mod mic;
+mod overlays;
+mod pip_keyframes;
+mod pip_layout;
+mod pip_style;
+mod privacy_mask;
+mod raster;
+mod recording_state;
+mod scenes;
+mod screenshot;
+mod settings;
+mod split_layout;
+mod system_audio;
+mod video_adjust;
+mod video_transform;
+
 // Constants for pipeline strings
-const CAMERA_PIPELINE: &str = "avfvideosrc device-index=0 ! video/x-raw,width=1280,height=720,framerate=30/1 ! videoconvert ! video/x-raw,format=RGBA,width=1280,height=720 ! queue leaky=downstream max-size-buffers=1 ! appsink name=sink sync=false drop=true max-buffers=1 emit-signals=true";
-const SCREEN_PIPELINE: &str = "avfvideosrc capture-screen=true capture-screen-cursor=true device-index={} ! videoconvert ! video/x-raw,format=RGBA,framerate=60/1 ! queue leaky=downstream max-size-buffers=1 ! appsink name=sink sync=false drop=true max-buffers=1 emit-signals=true";
+const CAMERA_PIPELINE: &str = "avfvideosrc device-index={} ! video/x-raw,width=1280,height=720,framerate=30/1 ! videoconvert ! video/x-raw,format=RGBA,width=1280,height=720 ! videobalance name=balance ! gamma name=gamma ! videoflip name=flip ! {overlays}queue leaky=downstream max-size-buffers=1 ! appsink name=sink sync=false drop=true max-buffers=1 emit-signals=true";
+const SCREEN_PIPELINE: &str = "avfvideosrc capture-screen=true capture-screen-cursor=true device-index={} ! videoconvert ! video/x-raw,format=RGBA,framerate=60/1 ! videobalance name=balance ! gamma name=gamma ! videoflip name=flip ! {overlays}queue leaky=downstream max-size-buffers=1 ! appsink name=sink sync=false drop=true max-buffers=1 emit-signals=true";
 const RECORDING_PIPELINE: &str = "
     matroskamux name=mux ! filesink name=filesink sync=false
     appsrc name=video_src format=time is-live=true ! videoconvert ! x264enc tune=zerolatency ! h264parse ! queue ! mux.
@@ -33,12 +78,27 @@ const RECORD_ON_ICON: &str = "\u{F059}";
 const RECORD_OFF_ICON: &str = "\u{F05A}";
 const MIC_ON_ICON: &str = "\u{EF50}";
 const MIC_OFF_ICON: &str = "\u{EF52}";
+const BEEP_PIPELINE: &str = "audiotestsrc wave=sine freq={} volume=0.4 samplesperbuffer=4410 num-buffers=2 ! audio/x-raw,rate=44100 ! audioconvert ! autoaudiosink";
+
+const PAUSE_ICON: &str = "\u{EFD6}";
+const SCREENSHOT_ICON: &str = "\u{EB31}";
+const PICKER_ICON: &str = "\u{F532}";
+const RESUME_ICON: &str = "\u{F009}";
+const MASK_ICON: &str = "\u{F108}";
+const ANNOTATE_ICON: &str = "\u{EFE0}";
+// how close a dragged PiP has to get to an anchor to snap to it, in UI points
+const PIP_SNAP_DISTANCE: f32 = 16.0;
+const PIP_RESIZE_HANDLE: f32 = 14.0;
+// smallest PiP width in video pixels
+const PIP_MIN_WIDTH: f32 = 64.0;
 
 struct ScreenCapApp {
     texture: Option<egui::TextureHandle>,
     frame_data: Arc<Mutex<Option<Vec<u8>>>>,
     dimensions: Arc<Mutex<ImageDimensions>>,
-    is_recording: bool,
+    recording_state: RecordingState,
+    recording_timer: RecordingTimer,
+    active_recording: Option<ActiveRecording>,
     is_mic_enabled: bool,
     pipeline: gst::Pipeline,
     current_device_idx: Option<usize>,
@@ -54,7 +114,6 @@ struct ScreenCapApp {
     update_dimensions_tx: mpsc::Sender<bool>,
     update_audio_tx: mpsc::Sender<bool>,
     audio_bin: Option<gst::Element>,
-    recording_files: Option<(String, String, String)>, // (video_file, audio_file, final_file)
     is_fullscreen: bool,
     // PiP state
     show_pip: bool,
@@ -62,15 +121,112 @@ struct ScreenCapApp {
     pip_frame_data: Arc<Mutex<Option<Vec<u8>>>>,
     pip_dimensions: Arc<Mutex<ImageDimensions>>,
     pip_pipeline: Option<gst::Pipeline>,
+    pip_device_idx: Option<usize>,
+    pip_error: Option<String>,
     pip_position: egui::Pos2,
-    pip_size: egui::Vec2,
-    pip_desired_size: egui::Vec2,
+    // PiP placement relative to the video frame, shared by preview and recording
+    pip_anchor: Option<PipAnchor>,
+    pip_margin: f32,
+    pip_offset: egui::Vec2,
+    pip_drag_pos: Option<egui::Pos2>,
+    // size of the visible PiP shape in video pixels, the capture is scaled to match
+    pip_video_size: egui::Vec2,
+    // degrees clockwise and alpha, set by the PiP timeline
+    pip_rotation: f32,
+    pip_opacity: f32,
+    timeline_view: TimelineView,
+    show_timeline: bool,
+    pip_animator: PipAnimator,
+    pip_capture_width: i32,
+    pip_style: Arc<Mutex<PipStyle>>,
+    // latest PiP frame with shape, border and shadow applied
+    pip_styled: Arc<Mutex<Option<StyledFrame>>>,
+    pip_chroma_key: Arc<Mutex<ChromaKey>>,
     recording_path: std::path::PathBuf,
-    main_pipeline: Option<gst::Pipeline>,
-    recording_pipeline: Option<gst::Pipeline>,
+    // countdown before recording
+    countdown_secs: u32,
+    countdown_beep: bool,
+    countdown_ends_at: Option<std::time::Instant>,
+    countdown_last_tick: Option<u64>,
+    beep_pipeline: Option<gst::Pipeline>,
+    // audio level meter
+    audio_level: AudioLevel,
+    audio_monitor: Option<gst::Pipeline>,
+    // set when the monitor failed, so it isn't relaunched until the audio setup changes
+    audio_monitor_failed: bool,
+    // microphone processing
+    mic_settings: MicSettings,
+    denoiser: Option<Denoiser>,
+    // desktop audio
+    system_audio_devices: Vec<MediaDeviceInfo>,
+    system_audio: SystemAudioSettings,
+    system_audio_level: AudioLevel,
+    audio_track_layout: AudioTrackLayout,
+    audio_track_language: String,
+    // audio-only recording
+    audio_only: bool,
+    audio_format: AudioFormat,
+    // screenshots
+    screenshot_format: ScreenshotFormat,
+    screenshot_include_pip: bool,
+    screenshot_annotate: bool,
+    annotate_view: Option<AnnotateView>,
+    // where the preview image was last drawn, in UI coordinates
+    preview_rect: Option<egui::Rect>,
+    // pixel color picker
+    picker_active: bool,
+    picker_last_copied: Option<String>,
+    // masks and cursor effects baked into the main frames
+    frame_layers: Arc<Mutex<FrameLayers>>,
+    // recent key combos shown over the main video
+    keystrokes: KeystrokeWatcher,
+    masks_hotkey_on: bool,
+    mask_drawing: bool,
+    mask_effect: MaskEffect,
+    mask_drag: Option<(egui::Pos2, egui::Pos2)>,
+    // live drawing over the main video, the strokes live in frame_layers
+    annotating: bool,
+    // auto zoom of the recorded main video, stepped on a thread of its own
+    auto_zoom: ZoomWatcher,
+    // scene switching, the crossfade picture feeds the compositor's top pad
+    active_scene: Option<usize>,
+    crossfade: Option<Crossfade>,
+    crossfade_frame: Arc<Mutex<Option<image::RgbaImage>>>,
+    // split layout background, rendered at the frame size and fed to the compositor's bottom pad
+    background_frame: Arc<Mutex<Option<image::RgbaImage>>>,
+    background_texture: Option<egui::TextureHandle>,
+    background_image: BackgroundImage,
+    // color and image revision the background frame was rendered with
+    background_key: Option<([u8; 3], u64)>,
+    // persisted preferences
+    settings: Settings,
+}
+
+struct RecordingFiles {
+    // written while recording, renamed once the muxer has finished
+    main_video: String,
+    final_file: String,
+}
+
+/// Pipeline and output files that belong to the recording in progress.
+struct ActiveRecording {
+    pipeline: gst::Pipeline,
+    files: RecordingFiles,
+    eos_received: bool,
+    /// Size of the recorded frame, fixed at the start while sources come and
+    /// go. None when recording audio only.
+    frame_size: Option<(i32, i32)>,
+}
+
+impl ActiveRecording {
+    fn set_state(&self, state: gst::State) {
+        if let Err(e) = self.pipeline.set_state(state) {
+            eprintln!("Error setting recording pipeline to {:?}: {:?}", state, e);
+        }
+    }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 struct MediaDeviceInfo {
     pipeline_id: u32,
     kind: MediaDeviceKind,
@@ -79,11 +235,23 @@ struct MediaDeviceInfo {
     device_id: Option<String>, // For audio devices
 }
 
-#[derive(Debug, PartialEq)]
+impl MediaDeviceInfo {
+    /// The capture element at the head of `setup_pipeline`, without any caps or sinks.
+    fn source_element(&self) -> &str {
+        self.setup_pipeline
+            .split(" ! ")
+            .next()
+            .unwrap_or(&self.setup_pipeline)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
 enum MediaDeviceKind {
     AudioInput,
     AudioOutput,
     VideoInput,
+    /// A captured screen, with its CoreGraphics display id.
+    Display(u32),
 }
 
 impl ScreenCapApp {
@@ -99,25 +267,45 @@ impl ScreenCapApp {
 
         // Get audio devices
         let audio_devices = get_audio_devices();
+        let denoiser = Denoiser::detect();
+        let system_audio_devices = system_audio::get_system_audio_devices(&audio_devices);
         let current_mic_idx = if !audio_devices.is_empty() {
             Some(0)
         } else {
             None
         };
 
+        let settings = Settings::load(cc.storage);
+        let frame_layers = Arc::new(Mutex::new(FrameLayers::default()));
+        // Shared with the PiP animator, which restyles the frames while recording
+        let pip_style = Arc::new(Mutex::new(PipStyle::default()));
+        let pip_styled = Arc::new(Mutex::new(None));
+        // Kept across source switches, so a running recording follows the new source
+        let frame_data = Arc::new(Mutex::new(None));
+        let image_dims = Arc::new(Mutex::new(ImageDimensions {
+            width: 0,
+            height: 0,
+        }));
+
         let recording_path = std::path::PathBuf::from(format!(
             "recording_{}.mp4",
             chrono::Local::now().format("%Y%m%d_%H%M%S")
         ));
 
-        match setup_gstreamer(0) {
+        let app = match setup_gstreamer(
+            0,
+            frame_layers.clone(),
+            frame_data.clone(),
+            image_dims.clone(),
+        ) {
             Ok(GstreamerSetup {
-                frame_data,
-                image_dims,
                 pipeline,
                 devices,
                 tx,
             }) => {
+                let keystrokes = KeystrokeWatcher::spawn(settings.keystrokes.clone());
+                keystrokes.set_pipeline(&pipeline);
+
                 let width;
                 let height;
                 {
@@ -135,7 +323,9 @@ impl ScreenCapApp {
                     frame_data,
                     dimensions: image_dims,
                     update_dimensions_tx: tx,
-                    is_recording: false,
+                    recording_state: RecordingState::Idle,
+                    recording_timer: RecordingTimer::default(),
+                    active_recording: None,
                     is_mic_enabled: true,
                     current_mic_idx,
                     pipeline,
@@ -143,7 +333,6 @@ impl ScreenCapApp {
                     show_settings: false,
                     image_size,
                     audio_bin: None,
-                    recording_files: None,
                     is_fullscreen: false,
                     // PiP state
                     show_pip: false,
@@ -154,12 +343,67 @@ impl ScreenCapApp {
                         height: 0,
                     })),
                     pip_pipeline: None,
+                    pip_device_idx: None,
+                    pip_error: None,
                     pip_position: egui::Pos2::default(),
-                    pip_size: egui::Vec2::default(),
-                    pip_desired_size: egui::vec2(320.0, 240.0),
+                    pip_anchor: Some(PipAnchor::TopLeft),
+                    pip_margin: 24.0,
+                    pip_offset: egui::Vec2::ZERO,
+                    pip_drag_pos: None,
+                    pip_video_size: egui::vec2(320.0, 180.0),
+                    pip_rotation: 0.0,
+                    pip_opacity: 1.0,
+                    timeline_view: TimelineView::default(),
+                    show_timeline: false,
+                    pip_animator: PipAnimator::spawn(pip_style.clone(), pip_styled.clone()),
+                    pip_capture_width: 0,
+                    pip_style: pip_style.clone(),
+                    pip_styled: pip_styled.clone(),
+                    pip_chroma_key: Arc::new(Mutex::new(ChromaKey::default())),
                     recording_path,
-                    main_pipeline: None,
-                    recording_pipeline: None,
+                    countdown_secs: 3,
+                    countdown_beep: true,
+                    countdown_ends_at: None,
+                    countdown_last_tick: None,
+                    beep_pipeline: None,
+                    audio_level: AudioLevel::default(),
+                    audio_monitor: None,
+                    audio_monitor_failed: false,
+                    mic_settings: MicSettings::default(),
+                    denoiser,
+                    system_audio_devices: system_audio_devices.clone(),
+                    system_audio: SystemAudioSettings {
+                        device_idx: (!system_audio_devices.is_empty()).then_some(0),
+                        ..Default::default()
+                    },
+                    system_audio_level: AudioLevel::default(),
+                    audio_track_layout: AudioTrackLayout::Mixed,
+                    audio_track_language: "en".to_string(),
+                    audio_only: false,
+                    audio_format: AudioFormat::Opus,
+                    screenshot_format: ScreenshotFormat::Png,
+                    screenshot_include_pip: true,
+                    screenshot_annotate: false,
+                    annotate_view: None,
+                    preview_rect: None,
+                    picker_active: false,
+                    picker_last_copied: None,
+                    frame_layers: frame_layers.clone(),
+                    keystrokes,
+                    masks_hotkey_on: false,
+                    mask_drawing: false,
+                    mask_effect: MaskEffect::Blur,
+                    mask_drag: None,
+                    annotating: false,
+                    auto_zoom: ZoomWatcher::spawn(settings.auto_zoom.clone()),
+                    active_scene: None,
+                    crossfade: None,
+                    crossfade_frame: Arc::new(Mutex::new(None)),
+                    background_frame: Arc::new(Mutex::new(None)),
+                    background_texture: None,
+                    background_image: BackgroundImage::default(),
+                    background_key: None,
+                    settings,
                 }
             }
             Err(err) => {
@@ -178,7 +422,9 @@ impl ScreenCapApp {
                     texture: None,
                     frame_data: Arc::new(Mutex::new(None)),
                     dimensions: default_dims,
-                    is_recording: false,
+                    recording_state: RecordingState::Idle,
+                    recording_timer: RecordingTimer::default(),
+                    active_recording: None,
                     is_mic_enabled: true,
                     current_mic_idx: None,
                     pipeline: dummy_pipeline.downcast::<gst::Pipeline>().unwrap(),
@@ -188,7 +434,6 @@ impl ScreenCapApp {
                     update_dimensions_tx: mpsc::channel().0,
                     update_audio_tx: mpsc::channel().0,
                     audio_bin: None,
-                    recording_files: None,
                     is_fullscreen: false,
                     // PiP state
                     show_pip: false,
@@ -199,468 +444,1734 @@ impl ScreenCapApp {
                         height: 0,
                     })),
                     pip_pipeline: None,
+                    pip_device_idx: None,
+                    pip_error: None,
                     pip_position: egui::Pos2::default(),
-                    pip_size: egui::Vec2::default(),
-                    pip_desired_size: egui::vec2(320.0, 240.0),
+                    pip_anchor: Some(PipAnchor::TopLeft),
+                    pip_margin: 24.0,
+                    pip_offset: egui::Vec2::ZERO,
+                    pip_drag_pos: None,
+                    pip_video_size: egui::vec2(320.0, 180.0),
+                    pip_rotation: 0.0,
+                    pip_opacity: 1.0,
+                    timeline_view: TimelineView::default(),
+                    show_timeline: false,
+                    pip_animator: PipAnimator::spawn(pip_style.clone(), pip_styled.clone()),
+                    pip_capture_width: 0,
+                    pip_style: pip_style.clone(),
+                    pip_styled: pip_styled.clone(),
+                    pip_chroma_key: Arc::new(Mutex::new(ChromaKey::default())),
                     recording_path,
-                    main_pipeline: None,
-                    recording_pipeline: None,
+                    countdown_secs: 3,
+                    countdown_beep: true,
+                    countdown_ends_at: None,
+                    countdown_last_tick: None,
+                    beep_pipeline: None,
+                    audio_level: AudioLevel::default(),
+                    audio_monitor: None,
+                    audio_monitor_failed: false,
+                    mic_settings: MicSettings::default(),
+                    denoiser,
+                    system_audio_devices: system_audio_devices.clone(),
+                    system_audio: SystemAudioSettings {
+                        device_idx: (!system_audio_devices.is_empty()).then_some(0),
+                        ..Default::default()
+                    },
+                    system_audio_level: AudioLevel::default(),
+                    audio_track_layout: AudioTrackLayout::Mixed,
+                    audio_track_language: "en".to_string(),
+                    audio_only: false,
+                    audio_format: AudioFormat::Opus,
+                    screenshot_format: ScreenshotFormat::Png,
+                    screenshot_include_pip: true,
+                    screenshot_annotate: false,
+                    annotate_view: None,
+                    preview_rect: None,
+                    picker_active: false,
+                    picker_last_copied: None,
+                    frame_layers: frame_layers.clone(),
+                    keystrokes: KeystrokeWatcher::spawn(settings.keystrokes.clone()),
+                    masks_hotkey_on: false,
+                    mask_drawing: false,
+                    mask_effect: MaskEffect::Blur,
+                    mask_drag: None,
+                    annotating: false,
+                    auto_zoom: ZoomWatcher::spawn(settings.auto_zoom.clone()),
+                    active_scene: None,
+                    crossfade: None,
+                    crossfade_frame: Arc::new(Mutex::new(None)),
+                    background_frame: Arc::new(Mutex::new(None)),
+                    background_texture: None,
+                    background_image: BackgroundImage::default(),
+                    background_key: None,
+                    settings,
                 }
             }
-        }
+        };
+        app.apply_source_settings();
+        app
     }
 
-    fn start_recording(&mut self) -> Result<(), anyhow::Error> {
-        // Create unique filenames for the recording
-        let timestamp = chrono::Local::now().format("%Y%m%d_%H%M%S");
-        let main_video = format!("recording_{}_main.mkv", timestamp);
-        let pip_video = format!("recording_{}_pip.mkv", timestamp);
-
-        // Create main video recording pipeline with high quality settings
-        let main_pipeline_str = format!(
-            "appsrc name=video_src format=time is-live=true do-timestamp=true ! \
-             videoconvert ! video/x-raw,format=I420 ! \
-             x264enc tune=zerolatency speed-preset=slower bitrate=8000 key-int-max=60 ! \
-             matroskamux name=mux ! filesink location={} \
-             osxaudiosrc ! audioconvert ! audioresample ! \
-             audio/x-raw,rate=44100,channels=2 ! \
-             avenc_aac bitrate=320000 ! queue ! mux.",
-            main_video
-        );
-
-        println!("Using main pipeline: {}", main_pipeline_str);
-
-        let main_pipeline = gst::parse::launch(&main_pipeline_str)
-            .map_err(|e| anyhow::anyhow!("Failed to create main recording pipeline: {:?}", e))?
-            .downcast::<gst::Pipeline>()
-            .map_err(|_| anyhow::anyhow!("Failed to downcast to Pipeline"))?;
-
-        // Set up main video source
-        if let Some(video_src) = main_pipeline.by_name("video_src") {
-            let video_src = video_src
-                .downcast::<gstreamer_app::AppSrc>()
-                .map_err(|_| anyhow::anyhow!("Failed to downcast to AppSrc"))?;
-
-            video_src.set_format(gst::Format::Time);
-            video_src.set_max_bytes(1);
-            video_src.set_do_timestamp(true);
-
-            let dims = self.dimensions.lock().unwrap();
-            let caps = gst::Caps::builder("video/x-raw")
-                .field("format", "RGBA")
-                .field("width", dims.width)
-                .field("height", dims.height)
-                .field("framerate", gst::Fraction::new(30, 1))
-                .build();
-            video_src.set_caps(Some(&caps));
-
-            let frame_data = self.frame_data.clone();
-            let video_src_weak = video_src.downgrade();
-
-            video_src.set_callbacks(
-                gstreamer_app::AppSrcCallbacks::builder()
-                    .need_data(move |_, _| {
-                        if let Some(src) = video_src_weak.upgrade() {
-                            if let Ok(guard) = frame_data.lock() {
-                                if let Some(buffer) = guard.as_ref() {
-                                    let mut gst_buffer = gst::Buffer::with_size(buffer.len())
-                                        .expect("Failed to allocate buffer");
-                                    {
-                                        let buffer_mut = gst_buffer.get_mut().unwrap();
-                                        let mut data = buffer_mut.map_writable().unwrap();
-                                        data.copy_from_slice(buffer);
-                                    }
-                                    let _ = src.push_buffer(gst_buffer);
-                                }
-                            }
-                        }
-                    })
-                    .build(),
-            );
+    fn apply_recording_event(&mut self, event: RecordingEvent) -> Result<(), anyhow::Error> {
+        let next = self.recording_state.transition(event)?;
+        match next {
+            RecordingState::Starting => self.recording_timer.reset(),
+            RecordingState::Recording => self.recording_timer.resume(),
+            _ => self.recording_timer.pause(),
         }
+        self.pip_animator.set_timer(self.recording_timer);
+        self.recording_state = next;
+        Ok(())
+    }
 
-        // Create PiP video recording pipeline if PiP is enabled
-        let pip_pipeline = if self.show_pip {
-            let pip_pipeline_str = format!(
-                "appsrc name=pip_src format=time is-live=true do-timestamp=true ! \
-                 videoconvert ! video/x-raw,format=I420 ! \
-                 x264enc tune=zerolatency speed-preset=slower bitrate=4000 key-int-max=60 ! \
-                 matroskamux ! filesink location={}",
-                pip_video
-            );
-
-            println!("Using PiP pipeline: {}", pip_pipeline_str);
-
-            let pipeline = gst::parse::launch(&pip_pipeline_str)
-                .map_err(|e| anyhow::anyhow!("Failed to create PiP recording pipeline: {:?}", e))?
-                .downcast::<gst::Pipeline>()
-                .map_err(|_| anyhow::anyhow!("Failed to downcast to Pipeline"))?;
-
-            if let Some(pip_src) = pipeline.by_name("pip_src") {
-                let pip_src = pip_src
-                    .downcast::<gstreamer_app::AppSrc>()
-                    .map_err(|_| anyhow::anyhow!("Failed to downcast to AppSrc"))?;
-
-                pip_src.set_format(gst::Format::Time);
-                pip_src.set_max_bytes(1);
-                pip_src.set_do_timestamp(true);
-
-                let dims = self.pip_dimensions.lock().unwrap();
-                let caps = gst::Caps::builder("video/x-raw")
-                    .field("format", "RGBA")
-                    .field("width", dims.width)
-                    .field("height", dims.height)
-                    .field("framerate", gst::Fraction::new(30, 1))
-                    .build();
-                pip_src.set_caps(Some(&caps));
-
-                let frame_data = self.pip_frame_data.clone();
-                let pip_src_weak = pip_src.downgrade();
-
-                pip_src.set_callbacks(
-                    gstreamer_app::AppSrcCallbacks::builder()
-                        .need_data(move |_, _| {
-                            if let Some(src) = pip_src_weak.upgrade() {
-                                if let Ok(guard) = frame_data.lock() {
-                                    if let Some(buffer) = guard.as_ref() {
-                                        let mut gst_buffer = gst::Buffer::with_size(buffer.len())
-                                            .expect("Failed to allocate buffer");
-                                        {
-                                            let buffer_mut = gst_buffer.get_mut().unwrap();
-                                            let mut data = buffer_mut.map_writable().unwrap();
-                                            data.copy_from_slice(buffer);
-                                        }
-                                        let _ = src.push_buffer(gst_buffer);
-                                    }
-                                }
-                            }
-                        })
-                        .build(),
-                );
+    fn toggle_recording(&mut self) {
+        let result = if self.recording_state == RecordingState::Countdown {
+            self.cancel_countdown()
+        } else if self.recording_state.can_stop() {
+            self.stop_recording()
+        } else if self.recording_state.can_start() {
+            if self.countdown_secs > 0 {
+                self.start_countdown()
+            } else {
+                self.start_recording()
             }
-
-            Some(pipeline)
         } else {
-            None
+            Ok(())
         };
 
-        // Start recording
-        main_pipeline.set_state(gst::State::Playing)?;
-        if let Some(pip_pipeline) = &pip_pipeline {
-            pip_pipeline.set_state(gst::State::Playing)?;
+        if let Err(e) = result {
+            eprintln!("Recording error: {:?}", e);
         }
+    }
 
-        self.recording_pipeline = Some(main_pipeline);
-        self.recording_files = Some((
-            main_video,
-            pip_video,
-            format!("recording_{}.mkv", timestamp),
-        ));
-        self.is_recording = true;
+    fn start_countdown(&mut self) -> Result<(), anyhow::Error> {
+        self.apply_recording_event(RecordingEvent::Countdown)?;
+        self.countdown_ends_at = Some(
+            std::time::Instant::now() + std::time::Duration::from_secs(self.countdown_secs as u64),
+        );
+        self.countdown_last_tick = None;
+        Ok(())
+    }
 
+    fn cancel_countdown(&mut self) -> Result<(), anyhow::Error> {
+        self.apply_recording_event(RecordingEvent::Cancel)?;
+        self.countdown_ends_at = None;
+        self.stop_beep();
         Ok(())
     }
 
-    fn stop_recording(&mut self) {
-        self.is_recording = false;
+    /// Seconds left on the countdown, rounded up so the overlay shows 3-2-1.
+    fn countdown_remaining(&self) -> Option<u64> {
+        let ends_at = self.countdown_ends_at?;
+        let remaining = ends_at.saturating_duration_since(std::time::Instant::now());
+        Some((remaining.as_millis() as u64).div_ceil(1000))
+    }
+
+    /// Beep on every tick and start recording the moment the countdown runs out.
+    fn update_countdown(&mut self) {
+        let Some(remaining) = self.countdown_remaining() else {
+            return;
+        };
 
-        if let Some((main_video, pip_video, final_file)) = self.recording_files.take() {
-            // Stop recording pipelines first
-            if let Some(recording_pipeline) = self.recording_pipeline.take() {
-                let _ = recording_pipeline.set_state(gst::State::Null);
+        if remaining == 0 {
+            self.countdown_ends_at = None;
+            self.stop_beep();
+            if let Err(e) = self.start_recording() {
+                eprintln!("Failed to start recording: {:?}", e);
             }
-            if let Some(pip_pipeline) = self.pip_pipeline.take() {
-                let _ = pip_pipeline.set_state(gst::State::Null);
+            return;
+        }
+
+        if self.countdown_last_tick != Some(remaining) {
+            self.countdown_last_tick = Some(remaining);
+            if self.countdown_beep {
+                // Higher pitch on the last tick
+                self.play_beep(if remaining == 1 { 1320.0 } else { 880.0 });
             }
+        }
+    }
 
-            // If PiP was enabled, merge the videos using FFmpeg
-            if self.show_pip {
-                // combine the videos, and output to a new file
-                //                 let compositor_pipeline = gst::parse::launch(&format!(
-                //                     "
-                // compositor name=comp sink_1::xpos=100 sink_1::ypos=50 ! videoconvert ! filesink location={} \
-                // filesrc location={} ! decodebin ! videoconvert ! video/x-raw,format=RGBA ! queue ! comp. \
-                // filesrc location={} ! decodebin ! videoconvert ! video/x-raw,format=RGBA ! queue ! comp.sink_1 \
-                // ",
-                //                     final_file.as_str(),
-                //                     main_video.as_str(),
-                //                     pip_video.as_str()
-                //                 ))
-                //                 .unwrap();
-
-                //                 compositor_pipeline.set_state(gst::State::Playing).unwrap();
-                //                 std::thread::sleep(std::time::Duration::from_secs(10));
-                //                 compositor_pipeline.set_state(gst::State::Null).unwrap();
-                //                 println!("Compositor pipeline finished");
-                //                 // let _ = std::fs::rename(main_video, final_file);
-
-                let status = std::process::Command::new("ffmpeg")
-                    .args([
-                        "-i",
-                        &main_video,
-                        "-i",
-                        &pip_video,
-                        "-filter_complex",
-                        "[0:v][1:v]overlay=x=20:y=20",
-                        "-c:v",
-                        "libx264",
-                        &final_file,
-                    ])
-                    .status()
-                    .expect("Failed to execute ffmpeg");
-
-                if status.success() {
-                    // Clean up temp files
-                    let _ = std::fs::remove_file(main_video);
-                    let _ = std::fs::remove_file(pip_video);
+    fn play_beep(&mut self, freq: f64) {
+        self.stop_beep();
+
+        let pipeline_str = BEEP_PIPELINE.replace("{}", &freq.to_string());
+        match gst::parse::launch(&pipeline_str) {
+            Ok(element) => {
+                let Ok(pipeline) = element.downcast::<gst::Pipeline>() else {
+                    return;
+                };
+                if let Err(e) = pipeline.set_state(gst::State::Playing) {
+                    eprintln!("Failed to play countdown beep: {:?}", e);
                 }
-            } else {
-                // If no PiP, just rename main video to final file
-                let _ = std::fs::rename(main_video, final_file);
+                self.beep_pipeline = Some(pipeline);
+            }
+            Err(e) => {
+                eprintln!("Failed to create beep pipeline: {:?}", e);
             }
         }
     }
 
-    pub fn get_current_frame(&self) -> Option<Vec<u8>> {
-        self.frame_data.lock().ok()?.as_ref().cloned()
+    fn stop_beep(&mut self) {
+        if let Some(pipeline) = self.beep_pipeline.take() {
+            let _ = pipeline.set_state(gst::State::Null);
+        }
     }
 
-    pub fn get_dimensions(&self) -> (i32, i32) {
-        let dims = self.dimensions.lock().unwrap();
-        (dims.width, dims.height)
-    }
+    fn toggle_pause(&mut self) {
+        let result = match self.recording_state {
+            RecordingState::Recording => self.pause_recording(),
+            RecordingState::Paused => self.resume_recording(),
+            _ => Ok(()),
+        };
 
-    pub fn get_pixel(&self, x: i32, y: i32) -> Option<[u8; 4]> {
-        let dims = self.dimensions.lock().unwrap();
-        if x < 0 || x >= dims.width || y < 0 || y >= dims.height {
-            return None;
+        if let Err(e) = result {
+            eprintln!("Recording error: {:?}", e);
         }
-
-        self.frame_data.lock().unwrap().as_ref().map(|frame| {
-            let idx = ((y * dims.width + x) * 4) as usize;
-            [frame[idx], frame[idx + 1], frame[idx + 2], frame[idx + 3]]
-        })
     }
 
-    fn switch_source(&mut self, device_idx: usize) {
-        // Stop the current pipeline first
-        if let Err(e) = self.pipeline.set_state(gst::State::Null) {
-            eprintln!("Error stopping pipeline: {:?}", e);
-        }
+    fn start_recording(&mut self) -> Result<(), anyhow::Error> {
+        self.apply_recording_event(RecordingEvent::Start)?;
 
-        // Start the new pipeline with error handling
-        match setup_gstreamer(device_idx) {
-            Ok(GstreamerSetup {
-                frame_data,
-                image_dims,
-                pipeline,
-                devices,
-                tx,
-            }) => {
-                self.frame_data = frame_data;
-                self.dimensions = image_dims;
-                self.pipeline = pipeline;
-                self.video_devices = devices;
-                self.update_dimensions_tx = tx;
-                self.current_device_idx = Some(device_idx);
+        let launched = if self.audio_only {
+            self.launch_audio_only_recording()
+        } else {
+            self.launch_recording()
+        };
 
-                // Update image size
-                let dims = self.dimensions.lock().unwrap();
-                self.image_size = egui::Vec2::new(dims.width as f32, dims.height as f32);
-                println!("Switched to device {}", device_idx);
+        match launched {
+            Ok(active) => {
+                // Nothing reads the preview while recording audio only
+                if self.audio_only {
+                    let _ = self.pipeline.set_state(gst::State::Paused);
+                }
+                if let Some((width, height)) = active.frame_size {
+                    self.auto_zoom
+                        .set_recording(&active.pipeline, width, height);
+                }
+                self.active_recording = Some(active);
+                Ok(())
             }
             Err(e) => {
-                eprintln!("Failed to start pipeline: {:?}", e);
+                self.apply_recording_event(RecordingEvent::Error(e.to_string()))?;
+                Err(e)
             }
         }
     }
 
-    fn switch_mic(&mut self, idx: usize) {
-        self.current_mic_idx = Some(idx);
-        if self.is_recording {
-            self.stop_recording();
-            self.start_recording();
-        }
-    }
+    fn launch_recording(&self) -> Result<ActiveRecording, anyhow::Error> {
+        // Create unique filenames for the recording
+        let timestamp = recording_timestamp();
+        let main_video = output_filename(&timestamp, Some("main"), "mkv");
 
-    fn current_device_label(&self) -> String {
-        self.current_device_idx
-            .and_then(|idx| self.video_devices.get(idx))
-            .map(|device| device.label.clone())
-            .unwrap_or_else(|| "FaceTime Camera".to_string())
-    }
+        // Create main video recording pipeline with high quality settings
+        // The mic checkbox drops the audio branch entirely, muting keeps a silent track
+        let audio_sources = self.audio_sources();
+        let audio_branch = audio_tracks::tracks(
+            &audio_sources,
+            self.audio_track_layout,
+            &self.audio_track_language,
+        );
 
-    fn current_mic_label(&self) -> String {
-        self.current_mic_idx
-            .and_then(|idx| self.audio_devices.get(idx))
-            .map(|device| device.label.clone())
-            .unwrap_or_else(|| "Default Microphone".to_string())
-    }
+        // The main video is scaled up inside a compositor of its own for the auto
+        // zoom, which keeps its caps fixed while the view moves,
+        // the styled PiP frames are composited live on top of it, and
+        // scene crossfades on top of everything. Split layouts put a background
+        // below it all. Every branch is always there, so scenes and layouts can
+        // bring them in without restarting the pipeline
+        let (width, height) = self.get_dimensions();
+        // `rotate` comes with gst-plugins-bad, only ask for it when the timeline turns the PiP
+        let pip_rotate = if !self.settings.pip_timeline.rotates() {
+            ""
+        } else if gst::ElementFactory::find("rotate").is_some() {
+            "rotate name=pip_rotate ! "
+        } else {
+            eprintln!("rotate element not found, the PiP won't turn in the recording");
+            ""
+        };
+        let main_pipeline_str = format!(
+            "compositor name=comp background=black \
+             sink_3::zorder=0 sink_0::zorder=1 sink_1::zorder=2 sink_2::zorder=3 ! \
+             video/x-raw,width={},height={} ! \
+             videoconvert ! video/x-raw,format=I420 ! \
+             x264enc tune=zerolatency speed-preset=slower bitrate=8000 key-int-max=60 ! \
+             matroskamux name=mux ! filesink location={} \
+             appsrc name=video_src format=time is-live=true do-timestamp=true ! \
+             videoconvert ! zoom.sink_0 compositor name=zoom background=black ! \
+             video/x-raw,width={},height={} ! queue ! comp.sink_0 \
+             appsrc name=pip_src format=time is-live=true do-timestamp=true ! \
+             videoconvert ! {}queue ! comp.sink_1 \
+             appsrc name=fade_src format=time is-live=true do-timestamp=true ! \
+             videoconvert ! queue ! comp.sink_2 \
+             appsrc name=background_src format=time is-live=true do-timestamp=true ! \
+             videoconvert ! queue ! comp.sink_3 \
+             {}",
+            width, height, main_video, width, height, pip_rotate, audio_branch
+        );
 
-    fn setup_pip_webcam(&mut self) -> Result<(), anyhow::Error> {
-        // Create webcam pipeline with 16:9 aspect ratio
-        let pipeline_str = "avfvideosrc device-index=0 ! \
-            video/x-raw,width=1280,height=720,framerate=30/1 ! \
-            videoscale ! capsfilter name=size ! \
-            videoconvert ! video/x-raw,format=RGBA ! \
-            appsink name=pip_sink sync=false drop=true max-buffers=1";
-
-        let pipeline = gst::parse::launch(pipeline_str)
-            .map_err(|e| anyhow::anyhow!("Failed to create PiP pipeline: {:?}", e))?
+        println!("Using main pipeline: {}", main_pipeline_str);
+
+        let main_pipeline = gst::parse::launch(&main_pipeline_str)
+            .map_err(|e| anyhow::anyhow!("Failed to create main recording pipeline: {:?}", e))?
             .downcast::<gst::Pipeline>()
             .map_err(|_| anyhow::anyhow!("Failed to downcast to Pipeline"))?;
 
-        let appsink = pipeline
-            .by_name("pip_sink")
-            .ok_or(anyhow::anyhow!("Failed to find pip_sink"))?
-            .downcast::<gstreamer_app::AppSink>()
-            .map_err(|_| anyhow::anyhow!("Failed to cast to AppSink"))?;
-
-        // Configure appsink
-        appsink.set_max_buffers(1);
-        appsink.set_drop(true);
-        appsink.set_sync(false);
+        // Set up main video source
+        let frame_data = self.frame_data.clone();
+        let dimensions = self.dimensions.clone();
+        attach_frame_source(&main_pipeline, "video_src", move || {
+            let frame = frame_data.lock().ok()?.clone()?;
+            let dims = dimensions.lock().ok()?;
+            Some((frame, dims.width, dims.height))
+        })?;
+
+        // Set up PiP source, already shaped and shadowed by the PiP appsink
+        let pip_styled = self.pip_styled.clone();
+        attach_frame_source(&main_pipeline, "pip_src", move || {
+            let guard = pip_styled.lock().ok()?;
+            Some(match guard.as_ref() {
+                Some(styled) => (styled.data.clone(), styled.width, styled.height),
+                None => blank_frame(),
+            })
+        })?;
+
+        // Set up the crossfade source, blank until a scene switch fades
+        let crossfade_frame = self.crossfade_frame.clone();
+        attach_frame_source(&main_pipeline, "fade_src", move || {
+            let guard = crossfade_frame.lock().ok()?;
+            Some(match guard.as_ref() {
+                Some(frame) => (
+                    frame.as_raw().clone(),
+                    frame.width() as i32,
+                    frame.height() as i32,
+                ),
+                None => blank_frame(),
+            })
+        })?;
+
+        // Set up the split layout background, blank while the PiP floats
+        let background_frame = self.background_frame.clone();
+        attach_frame_source(&main_pipeline, "background_src", move || {
+            let guard = background_frame.lock().ok()?;
+            Some(match guard.as_ref() {
+                Some(frame) => (
+                    frame.as_raw().clone(),
+                    frame.width() as i32,
+                    frame.height() as i32,
+                ),
+                None => blank_frame(),
+            })
+        })?;
+        self.update_recording_layout_on(&main_pipeline);
+
+        mic::apply(&self.mic_settings, &main_pipeline);
+        system_audio::apply(&self.system_audio, &main_pipeline);
+
+        let active = ActiveRecording {
+            pipeline: main_pipeline,
+            files: RecordingFiles {
+                main_video,
+                final_file: output_filename(&timestamp, None, "mkv"),
+            },
+            eos_received: false,
+            frame_size: Some((width, height)),
+        };
 
-        let frame_data = self.pip_frame_data.clone();
-        let dimensions = self.pip_dimensions.clone();
+        // Start recording, the bus reports when the pipeline is actually playing
+        if let Err(e) = active.pipeline.set_state(gst::State::Playing) {
+            active.set_state(gst::State::Null);
+            return Err(e.into());
+        }
 
-        // Set up callbacks
-        appsink.set_callbacks(
-            gstreamer_app::AppSinkCallbacks::builder()
-                .new_sample(move |sink| {
-                    let sample = sink.pull_sample().map_err(|_| gst::FlowError::Error)?;
+        Ok(active)
+    }
 
-                    // Update dimensions from caps
-                    if let Some(caps) = sample.caps() {
-                        if let Some(s) = caps.structure(0) {
-                            let mut dims = dimensions.lock().unwrap();
-                            dims.width = s.get::<i32>("width").unwrap_or(1280);
-                            dims.height = s.get::<i32>("height").unwrap_or(720);
-                        }
-                    }
+    /// Record only the selected audio sources, without touching the video appsrc.
+    fn launch_audio_only_recording(&self) -> Result<ActiveRecording, anyhow::Error> {
+        let audio_sources = self.audio_sources();
+        if audio_sources.is_empty() {
+            return Err(anyhow::anyhow!(
+                "Audio-only recording needs the microphone or system audio enabled"
+            ));
+        }
 
-                    let buffer = sample.buffer().ok_or(gst::FlowError::Error)?;
-                    let map = buffer.map_readable().map_err(|_| gst::FlowError::Error)?;
+        let timestamp = recording_timestamp();
+        let extension = self.audio_format.extension();
+        let main_audio = output_filename(&timestamp, Some("main"), extension);
 
-                    let mut data = frame_data.lock().unwrap();
-                    *data = Some(map.as_ref().to_vec());
+        let pipeline_str = audio_only::pipeline(&audio_sources, self.audio_format, &main_audio);
+        println!("Using audio-only pipeline: {}", pipeline_str);
 
-                    Ok(gst::FlowSuccess::Ok)
-                })
-                .build(),
-        );
+        let pipeline = gst::parse::launch(&pipeline_str)
+            .map_err(|e| anyhow::anyhow!("Failed to create audio recording pipeline: {:?}", e))?
+            .downcast::<gst::Pipeline>()
+            .map_err(|_| anyhow::anyhow!("Failed to downcast to Pipeline"))?;
 
-        // Set initial PiP window position and size (16:9 ratio)
-        self.pip_position = egui::pos2(20.0, 20.0);
-        self.pip_size = egui::vec2(320.0, 180.0); // 16:9 ratio
-        self.pip_desired_size = self.pip_size;
+        mic::apply(&self.mic_settings, &pipeline);
+        system_audio::apply(&self.system_audio, &pipeline);
+
+        let active = ActiveRecording {
+            pipeline,
+            files: RecordingFiles {
+                main_video: main_audio,
+                final_file: output_filename(&timestamp, None, extension),
+            },
+            eos_received: false,
+            frame_size: None,
+        };
 
-        // Set initial caps with 16:9 aspect ratio
-        if let Some(caps_filter) = pipeline.by_name("size") {
-            let caps = gst::Caps::builder("video/x-raw")
-                .field("width", 320i32)
-                .field("height", 180i32) // Maintains 16:9
-                .build();
-            caps_filter.set_property("caps", &caps);
+        if let Err(e) = active.pipeline.set_state(gst::State::Playing) {
+            active.set_state(gst::State::Null);
+            return Err(e.into());
         }
 
-        // Start the pipeline
-        pipeline.set_state(gst::State::Playing)?;
+        Ok(active)
+    }
 
-        self.pip_pipeline = Some(pipeline);
-        self.show_pip = true;
+    /// Raw audio branches for every enabled source, titled for their tracks.
+    fn audio_sources(&self) -> Vec<(&'static str, String)> {
+        [
+            self.mic_branch().map(|branch| ("Microphone", branch)),
+            self.system_audio_branch()
+                .map(|branch| ("System Audio", branch)),
+        ]
+        .into_iter()
+        .flatten()
+        .collect()
+    }
 
+    fn pause_recording(&mut self) -> Result<(), anyhow::Error> {
+        self.apply_recording_event(RecordingEvent::Pause)?;
+        if let Some(active) = &self.active_recording {
+            active.set_state(gst::State::Paused);
+        }
         Ok(())
     }
 
-    fn update_pip_size(&mut self) {
-        if let Some(pipeline) = &self.pip_pipeline {
-            if let Some(caps_filter) = pipeline.by_name("size") {
-                // Calculate size maintaining 16:9 aspect ratio
-                let width = ((self.pip_size.x as i32 + 8) / 16) * 16;
-                let height = width * 9 / 16; // Force 16:9 ratio
-
-                // Ensure minimum size (16:9)
-                let width = width.max(320);
-                let height = height.max(180);
-
-                let caps = gst::Caps::builder("video/x-raw")
-                    .field("width", width)
-                    .field("height", height)
-                    .build();
-
-                caps_filter.set_property("caps", &caps);
-            }
+    fn resume_recording(&mut self) -> Result<(), anyhow::Error> {
+        self.apply_recording_event(RecordingEvent::Resume)?;
+        if let Some(active) = &self.active_recording {
+            active.set_state(gst::State::Playing);
         }
+        Ok(())
     }
 
-    fn toggle_pip(&mut self) {
-        if self.show_pip {
-            // Stop PiP pipeline
-            if let Some(pipeline) = self.pip_pipeline.take() {
-                let _ = pipeline.set_state(gst::State::Null);
-            }
-            self.show_pip = false;
-        } else {
-            // Start PiP pipeline
-            if let Err(e) = self.setup_pip_webcam() {
-                eprintln!("Failed to start PiP webcam: {:?}", e);
-            }
+    fn stop_recording(&mut self) -> Result<(), anyhow::Error> {
+        self.apply_recording_event(RecordingEvent::Stop)?;
+
+        // Send EOS so the muxer can write its headers, finalizing happens once
+        // the pipeline has reported EOS on its bus
+        if let Some(active) = &self.active_recording {
+            active.set_state(gst::State::Playing);
+            active.pipeline.send_event(gst::event::Eos::new());
         }
+        Ok(())
     }
-}
 
-impl eframe::App for ScreenCapApp {
-    fn on_exit(&mut self, _gl: Option<&eframe::glow::Context>) {
-        println!("On exit");
-        // Stop recording if active
-        if self.is_recording {
-            if let Some(tee) = self.pipeline.by_name("t") {
-                tee.set_property("allow-not-linked", false);
-            }
-        }
+    /// Drain the recording buses and drive the state machine from their messages.
+    fn poll_recording_bus(&mut self) {
+        let Some(active) = self.active_recording.as_mut() else {
+            return;
+        };
 
-        // Stop PiP pipeline if active
-        if let Some(pipeline) = self.pip_pipeline.take() {
-            if let Err(e) = pipeline.set_state(gst::State::Null) {
-                eprintln!("Error stopping PiP pipeline: {:?}", e);
+        let mut events = Vec::new();
+        let pipeline = active.pipeline.clone();
+        if let Some(bus) = pipeline.bus() {
+            while let Some(msg) = bus.pop() {
+                if self.audio_level.update_from_message(&msg, "mic_level")
+                    || self
+                        .system_audio_level
+                        .update_from_message(&msg, "system_level")
+                {
+                    continue;
+                }
+                match msg.view() {
+                    gst::MessageView::StateChanged(state_changed) => {
+                        let from_pipeline = msg
+                            .src()
+                            .map(|src| src == pipeline.upcast_ref::<gst::Object>())
+                            .unwrap_or(false);
+                        if from_pipeline && state_changed.current() == gst::State::Playing {
+                            events.push(RecordingEvent::PipelinePlaying);
+                        }
+                    }
+                    gst::MessageView::Eos(_) => {
+                        active.eos_received = true;
+                    }
+                    gst::MessageView::Error(err) => {
+                        events.push(RecordingEvent::Error(format!(
+                            "{} ({:?})",
+                            err.error(),
+                            err.debug()
+                        )));
+                    }
+                    _ => {}
+                }
             }
         }
 
-        // Stop the main pipeline
-        if let Err(e) = self.pipeline.set_state(gst::State::Null) {
-            eprintln!("Error stopping pipeline: {:?}", e);
+        if active.eos_received {
+            events.push(RecordingEvent::Eos);
         }
 
-        std::process::exit(0);
+        for event in events {
+            self.handle_recording_bus_event(event);
+        }
     }
 
-    fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
-        // Add keyboard shortcuts
-        if ctx.input(|i| i.modifiers.command) {
-            if ctx.input(|i| i.key_pressed(egui::Key::R)) {
-                // Cmd+R to start/stop recording
-                if self.is_recording {
-                    self.stop_recording();
-                } else {
-                    self.start_recording();
+    fn handle_recording_bus_event(&mut self, event: RecordingEvent) {
+        // The bus can repeat events, e.g. playing again after a resume
+        if self.recording_state.transition(event.clone()).is_err() {
+            debug!("Ignoring {:?} in {:?}", event, self.recording_state);
+            return;
+        }
+
+        let event = match event {
+            RecordingEvent::Eos => match self.finalize_recording() {
+                Ok(()) => RecordingEvent::Eos,
+                Err(e) => RecordingEvent::Error(e.to_string()),
+            },
+            RecordingEvent::Error(msg) => {
+                eprintln!("Recording failed: {}", msg);
+                if let Some(active) = self.active_recording.take() {
+                    active.set_state(gst::State::Null);
                 }
+                self.auto_zoom.clear_recording();
+                RecordingEvent::Error(msg)
             }
+            event => event,
+        };
+
+        if let Err(e) = self.apply_recording_event(event) {
+            eprintln!("Recording error: {:?}", e);
         }
 
-        // Set dark theme with custom colors
-        ctx.set_visuals(egui::Visuals::dark());
+        // The preview is paused during audio-only recordings
+        if !self.recording_state.is_active() {
+            let _ = self.pipeline.set_state(gst::State::Playing);
+        }
+    }
 
-        // Process frame data and update texture
-        if let Ok(frame_guard) = self.frame_data.lock() {
-            if let Some(buffer) = frame_guard.as_ref() {
-                let dims = self.dimensions.lock().unwrap();
-                let expected_size = (dims.width * dims.height * 4) as usize;
+    /// Tear down the finished pipeline and move the recording to its final name.
+    fn finalize_recording(&mut self) -> Result<(), anyhow::Error> {
+        let Some(active) = self.active_recording.take() else {
+            return Ok(());
+        };
+        active.set_state(gst::State::Null);
+        self.auto_zoom.clear_recording();
 
-                if buffer.len() == expected_size {
-                    let color_image = egui::ColorImage::from_rgba_unmultiplied(
-                        [dims.width as usize, dims.height as usize],
-                        buffer,
+        let RecordingFiles {
+            main_video,
+            final_file,
+        } = active.files;
+        std::fs::rename(main_video, final_file)?;
+
+        Ok(())
+    }
+
+    /// Where the styled PiP frame lands in the main video, in frame pixels.
+    /// Includes the transparent shadow margin around the shape.
+    fn pip_canvas_video_rect(&self) -> Option<egui::Rect> {
+        // Nothing to place until the PiP has produced a frame
+        self.pip_styled.lock().unwrap().as_ref()?;
+        let inset = egui::Vec2::splat(self.pip_inset());
+        Some(egui::Rect::from_min_size(
+            (self.pip_offset * self.frame_size()).to_pos2(),
+            self.pip_video_size + inset * 2.0,
+        ))
+    }
+
+    /// Size of the recorded frame: the running recording's, or the main source's.
+    fn frame_dimensions(&self) -> (i32, i32) {
+        self.active_recording
+            .as_ref()
+            .and_then(|active| active.frame_size)
+            .unwrap_or_else(|| self.get_dimensions())
+    }
+
+    fn frame_size(&self) -> egui::Vec2 {
+        let (width, height) = self.frame_dimensions();
+        egui::vec2(width as f32, height as f32)
+    }
+
+    fn source_size(&self) -> egui::Vec2 {
+        let (width, height) = self.get_dimensions();
+        egui::vec2(width as f32, height as f32)
+    }
+
+    /// Where the main video lands in the recorded frame, all of it unless split
+    /// or a scene switched to a source of another size while recording.
+    fn main_video_rect(&self) -> egui::Rect {
+        let frame = self.frame_size();
+        let source = self.source_size();
+        let full = egui::Rect::from_min_size(egui::Pos2::ZERO, frame);
+        match self.settings.split_layout.cells(frame) {
+            Some((cell, _)) => split_layout::fit(cell, source.x / source.y.max(1.0)),
+            None if source == frame => full,
+            None => split_layout::fit(full, source.x / source.y.max(1.0)),
+        }
+    }
+
+    /// The main video's rect on the preview, which shows the whole recorded frame.
+    fn main_preview_rect(&self) -> Option<egui::Rect> {
+        let frame = egui::Rect::from_min_size(egui::Pos2::ZERO, self.frame_size());
+        Some(
+            egui::emath::RectTransform::from_to(frame, self.preview_rect?)
+                .transform_rect(self.main_video_rect()),
+        )
+    }
+
+    /// Render the split layout's background at the frame size, again when its
+    /// color or image changed or the frame changed size.
+    fn sync_background(&mut self, ctx: &egui::Context) {
+        let (width, height) = self.frame_dimensions();
+        let mut frame = self.background_frame.lock().unwrap();
+        let layout = &self.settings.split_layout;
+        if !layout.is_split() || width <= 0 || height <= 0 {
+            *frame = None;
+            self.background_texture = None;
+            return;
+        }
+        let size = (width as u32, height as u32);
+        let (revision, cover) =
+            self.background_image
+                .fitted(&layout.background_image, size.0, size.1);
+        let key = (layout.background, revision);
+        if self.background_key == Some(key)
+            && frame
+                .as_ref()
+                .is_some_and(|frame| frame.dimensions() == size)
+        {
+            return;
+        }
+
+        let background = layout.render_background(cover.as_deref(), size.0, size.1);
+        self.background_texture = Some(ctx.load_texture(
+            "layout-background",
+            egui::ColorImage::from_rgba_unmultiplied(
+                [size.0 as usize, size.1 as usize],
+                background.as_raw(),
+            ),
+            egui::TextureOptions::default(),
+        ));
+        *frame = Some(background);
+        self.background_key = Some(key);
+    }
+
+    /// Width of the shadow margin around the PiP shape, in video pixels.
+    /// PiP frames are captured at their size in the video, so this is the latest
+    /// frame's padding, or the style's until one arrives.
+    fn pip_inset(&self) -> f32 {
+        let padding = self
+            .pip_styled
+            .lock()
+            .unwrap()
+            .as_ref()
+            .map(|styled| styled.padding);
+        padding.unwrap_or_else(|| self.pip_style.lock().unwrap().padding()) as f32
+    }
+
+    /// Aspect ratio of the visible PiP shape: the camera's own, or square when cropped.
+    fn pip_aspect_ratio(&self) -> f32 {
+        let (width, height) = self.pip_camera_size();
+        self.pip_style.lock().unwrap().shape_aspect(width, height)
+    }
+
+    fn pip_camera_size(&self) -> (i32, i32) {
+        let dims = self.pip_dimensions.lock().unwrap();
+        (dims.width, dims.height)
+    }
+
+    /// Place the PiP window over the preview, following its anchor if it has one.
+    fn layout_pip(&mut self) {
+        // The width is what the user picked, the height follows the source
+        self.pip_video_size.y = self.pip_video_size.x / self.pip_aspect_ratio();
+        self.update_pip_size();
+
+        let Some(preview_rect) = self.preview_rect else {
+            return;
+        };
+
+        // Split layouts fit the shape into its cell, with the shadow margin around it
+        let frame = self.frame_size();
+        if let Some((_, cell)) = self.settings.split_layout.cells(frame) {
+            let shape = split_layout::fit(cell, self.pip_aspect_ratio());
+            self.pip_video_size = shape.size();
+            self.update_pip_size();
+            let inset = egui::Vec2::splat(self.pip_inset());
+            self.pip_offset = (shape.min - inset).to_vec2() / frame;
+        } else if let (Some(anchor), Some(canvas)) = (self.pip_anchor, self.pip_canvas_video_rect())
+        {
+            // Anchors and margins apply to the visible shape, not its shadow
+            let inset = egui::Vec2::splat(self.pip_inset());
+            let pos = anchor.position(frame, canvas.size() - inset * 2.0, self.pip_margin) - inset;
+            self.pip_offset = pos.to_vec2() / frame;
+        }
+
+        self.pip_position = preview_rect.min + self.pip_offset * preview_rect.size();
+    }
+
+    /// Move the PiP to where it is being dragged on the preview, snapping to anchors.
+    fn drag_pip_to(&mut self, pos: egui::Pos2) {
+        let (Some(preview_rect), Some(canvas)) = (self.preview_rect, self.pip_canvas_video_rect())
+        else {
+            return;
+        };
+
+        let frame = self.frame_size();
+        let scale = frame.x / preview_rect.width();
+        let pos = ((pos - preview_rect.min) * scale).to_pos2();
+        let inset = egui::Vec2::splat(self.pip_inset());
+
+        self.pip_anchor = pip_layout::snap(
+            pos + inset,
+            frame,
+            canvas.size() - inset * 2.0,
+            self.pip_margin,
+            PIP_SNAP_DISTANCE * scale,
+        );
+        self.pip_offset = pip_layout::clamp(pos, frame, canvas.size()).to_vec2() / frame;
+    }
+
+    /// The PiP as it is placed now, for storing as a keyframe.
+    fn current_pip_keyframe(&self) -> PipKeyframe {
+        PipKeyframe {
+            time: 0.0,
+            offset: [self.pip_offset.x, self.pip_offset.y],
+            width: self.pip_video_size.x,
+            rotation: self.pip_rotation,
+            opacity: self.pip_opacity,
+            border_width: self.pip_style.lock().unwrap().border_width,
+        }
+    }
+
+    /// Put the PiP where the timeline has it at `time`, if it is animated.
+    fn apply_pip_timeline(&mut self, time: f32) {
+        let Some(keyframe) = self.settings.pip_timeline.sample(time) else {
+            return;
+        };
+        self.pip_anchor = None;
+        self.pip_offset = egui::vec2(keyframe.offset[0], keyframe.offset[1]);
+        self.pip_video_size.x = keyframe.width.max(PIP_MIN_WIDTH);
+        self.pip_rotation = keyframe.rotation;
+        self.pip_opacity = keyframe.opacity.clamp(0.0, 1.0);
+        self.pip_style.lock().unwrap().border_width = keyframe.border_width;
+    }
+
+    /// Where the timeline animates the recording's PiP: while recording video
+    /// with the PiP floating and keyframes to follow.
+    fn pip_animation_target(&self) -> Option<AnimationTarget> {
+        if !self.show_pip
+            || self.settings.split_layout.is_split()
+            || self.settings.pip_timeline.sample(0.0).is_none()
+        {
+            return None;
+        }
+        let active = self.active_recording.as_ref()?;
+        let (width, height) = active.frame_size?;
+        Some(AnimationTarget {
+            recording: active.pipeline.clone(),
+            capture: self.pip_pipeline.clone()?,
+            frame: egui::vec2(width as f32, height as f32),
+            camera: self.pip_camera_size(),
+        })
+    }
+
+    fn pip_animated(&self) -> bool {
+        self.pip_animation_target().is_some()
+    }
+
+    /// Hand the recording's PiP to the animator while the timeline moves it.
+    fn sync_pip_animator(&self) {
+        self.pip_animator.set_timeline(&self.settings.pip_timeline);
+        self.pip_animator.set_target(self.pip_animation_target());
+    }
+
+    /// Keep the compositor's PiP pad in sync with the PiP window on the preview.
+    fn update_recording_layout(&self) {
+        if let Some(active) = &self.active_recording {
+            self.update_recording_layout_on(&active.pipeline);
+        }
+    }
+
+    fn update_recording_layout_on(&self, pipeline: &gst::Pipeline) {
+        let (width, height) = self.frame_dimensions();
+        let pad = |name: &str| {
+            pipeline
+                .by_name("comp")
+                .and_then(|comp| comp.static_pad(name))
+        };
+
+        if let Some(pad) = pad("sink_3") {
+            pad.set_property("width", width);
+            pad.set_property("height", height);
+            let split = self.settings.split_layout.is_split();
+            pad.set_property("alpha", if split { 1.0f64 } else { 0.0 });
+        }
+
+        if let Some(pad) = pad("sink_0") {
+            let rect = self.main_video_rect();
+            pad.set_property("xpos", rect.left().round() as i32);
+            pad.set_property("ypos", rect.top().round() as i32);
+            pad.set_property("width", rect.width().round() as i32);
+            pad.set_property("height", rect.height().round() as i32);
+        }
+
+        if let Some(pad) = pad("sink_2") {
+            let alpha = self
+                .crossfade
+                .as_ref()
+                .map(|fade| fade.alpha())
+                .unwrap_or(0.0);
+            pad.set_property("width", width);
+            pad.set_property("height", height);
+            pad.set_property("alpha", alpha as f64);
+        }
+
+        // The animator moves the PiP while the timeline does
+        if self.pip_animated() {
+            return;
+        }
+
+        if let Some(rotate) = pipeline.by_name("pip_rotate") {
+            rotate.set_property("angle", (self.pip_rotation as f64).to_radians());
+        }
+
+        let Some(pad) = pad("sink_1") else {
+            return;
+        };
+
+        match self.pip_canvas_video_rect().filter(|_| self.show_pip) {
+            Some(rect) => {
+                pad.set_property("xpos", rect.left().round() as i32);
+                pad.set_property("ypos", rect.top().round() as i32);
+                pad.set_property("width", rect.width().round() as i32);
+                pad.set_property("height", rect.height().round() as i32);
+                pad.set_property("alpha", self.pip_opacity as f64);
+            }
+            None => pad.set_property("alpha", 0.0f64),
+        }
+    }
+
+    /// Stop an active recording and wait for it to finish, used when the app exits.
+    fn finish_recording_blocking(&mut self) {
+        if self.recording_state.can_stop() {
+            if let Err(e) = self.stop_recording() {
+                eprintln!("Error stopping recording: {:?}", e);
+            }
+        }
+
+        let deadline = std::time::Instant::now() + std::time::Duration::from_secs(5);
+        while self.recording_state == RecordingState::Finalizing
+            && std::time::Instant::now() < deadline
+        {
+            self.poll_recording_bus();
+            std::thread::sleep(std::time::Duration::from_millis(20));
+        }
+    }
+
+    pub fn get_current_frame(&self) -> Option<Vec<u8>> {
+        self.frame_data.lock().ok()?.as_ref().cloned()
+    }
+
+    pub fn get_dimensions(&self) -> (i32, i32) {
+        let dims = self.dimensions.lock().unwrap();
+        (dims.width, dims.height)
+    }
+
+    /// The latest frame, with the PiP drawn where it sits on the preview if asked for.
+    fn compose_frame(&self, include_pip: bool) -> Result<image::RgbaImage, anyhow::Error> {
+        let (width, height) = self.get_dimensions();
+        let frame = self
+            .get_current_frame()
+            .ok_or_else(|| anyhow::anyhow!("No frame to capture"))?;
+        let mut image = image::RgbaImage::from_raw(width as u32, height as u32, frame)
+            .ok_or_else(|| anyhow::anyhow!("Frame does not match {}x{}", width, height))?;
+
+        // Split layouts place the main video on their background, as recorded,
+        // and a recording letterboxes a source of another size
+        let frame_size = self.frame_size();
+        let main_rect = self.main_video_rect();
+        let background = self.background_frame.lock().unwrap().clone().or_else(|| {
+            (main_rect.size() != frame_size).then(|| {
+                image::RgbaImage::from_pixel(
+                    frame_size.x as u32,
+                    frame_size.y as u32,
+                    image::Rgba([0, 0, 0, 255]),
+                )
+            })
+        });
+        if let Some(mut background) = background {
+            screenshot::composite(&mut background, &image, main_rect);
+            image = background;
+        }
+
+        if include_pip && self.show_pip {
+            if let Some(rect) = self.pip_canvas_video_rect() {
+                let pip = self.pip_styled.lock().unwrap().as_ref().and_then(|styled| {
+                    image::RgbaImage::from_raw(
+                        styled.width as u32,
+                        styled.height as u32,
+                        styled.data.clone(),
+                    )
+                });
+                if let Some(pip) = pip {
+                    screenshot::composite(&mut image, &pip, rect);
+                }
+            }
+        }
+        Ok(image)
+    }
+
+    /// Save the latest frame, with the PiP drawn where it sits on the preview.
+    fn take_screenshot(&mut self) -> Result<(), anyhow::Error> {
+        let image = self.compose_frame(self.screenshot_include_pip)?;
+        let path = output_filename(
+            &screenshot_timestamp(),
+            Some("screenshot"),
+            self.screenshot_format.extension(),
+        );
+        screenshot::save(&image, self.screenshot_format, &path)?;
+        println!("Saved screenshot to {}", path);
+
+        if self.screenshot_annotate {
+            self.annotate_view = Some(AnnotateView::new(image, path, self.screenshot_format));
+        }
+        Ok(())
+    }
+
+    pub fn get_pixel(&self, x: i32, y: i32) -> Option<[u8; 4]> {
+        let dims = self.dimensions.lock().unwrap();
+        if x < 0 || x >= dims.width || y < 0 || y >= dims.height {
+            return None;
+        }
+
+        // The frame and its dimensions are updated under separate locks, so a
+        // resolution change can leave the frame shorter than they say
+        let idx = ((y * dims.width + x) * 4) as usize;
+        let frame = self.frame_data.lock().unwrap();
+        let pixel = frame.as_ref()?.get(idx..idx + 4)?;
+        Some([pixel[0], pixel[1], pixel[2], pixel[3]])
+    }
+
+    /// Map a point on the preview image to frame pixel coordinates.
+    fn preview_to_source(&self, pos: egui::Pos2) -> Option<(i32, i32)> {
+        let rect = self.main_preview_rect()?;
+        if !rect.contains(pos) {
+            return None;
+        }
+        let (width, height) = self.get_dimensions();
+        let mut relative = (pos - rect.min) / rect.size();
+        if self.mirrors_preview_only(false) {
+            relative.x = 1.0 - relative.x;
+        }
+        Some((
+            ((relative.x * width as f32) as i32).min(width - 1),
+            ((relative.y * height as f32) as i32).min(height - 1),
+        ))
+    }
+
+    fn toggle_picker(&mut self) {
+        self.picker_active = !self.picker_active;
+        self.picker_last_copied = None;
+        if self.picker_active {
+            self.mask_drawing = false;
+            self.annotating = false;
+        }
+    }
+
+    fn main_source_label(&self) -> Option<String> {
+        self.current_device_idx
+            .and_then(|idx| self.video_devices.get(idx))
+            .map(|device| device.label.clone())
+    }
+
+    fn pip_source_label(&self) -> Option<String> {
+        self.pip_device()
+            .and_then(|idx| self.video_devices.get(idx))
+            .map(|device| device.label.clone())
+    }
+
+    /// Push the saved adjustments and transforms of each running source into its pipeline.
+    fn apply_source_settings(&self) {
+        if let Some(label) = self.main_source_label() {
+            self.settings
+                .video_adjustments(&label)
+                .apply(&self.pipeline);
+            self.settings.video_transform(&label).apply(&self.pipeline);
+        }
+        self.settings.overlays.apply(&self.pipeline);
+        self.sync_privacy_masks();
+        self.sync_cursor_layer();
+        self.sync_auto_zoom_display();
+        self.sync_annotation_fade();
+        if let (Some(pipeline), Some(label)) = (&self.pip_pipeline, self.pip_source_label()) {
+            self.settings.video_adjustments(&label).apply(pipeline);
+            self.settings.video_transform(&label).apply(pipeline);
+            self.pip_chroma_key.lock().unwrap().apply(pipeline);
+        }
+    }
+
+    /// Point the auto zoom at the captured display, if the main source is one.
+    fn sync_auto_zoom_display(&self) {
+        let transform = self
+            .main_source_label()
+            .map(|label| self.settings.video_transform(&label))
+            .unwrap_or_default();
+        let display = self
+            .main_display()
+            .map(|id| (unsafe { CGDisplayBounds(id) }, transform));
+        self.auto_zoom.set_display(display);
+    }
+
+    /// Hand the main source's masks that are currently on to the frame callback.
+    fn sync_privacy_masks(&self) {
+        let masks = self
+            .main_source_label()
+            .and_then(|label| self.settings.privacy_masks.get(&label))
+            .map(|masks| {
+                masks
+                    .iter()
+                    .filter(|mask| !mask.hotkey || self.masks_hotkey_on)
+                    .cloned()
+                    .collect()
+            })
+            .unwrap_or_default();
+        self.frame_layers.lock().unwrap().masks = masks;
+    }
+
+    /// The CoreGraphics display id when the main source is a screen.
+    fn main_display(&self) -> Option<u32> {
+        self.current_device_idx
+            .and_then(|idx| self.video_devices.get(idx))
+            .and_then(|device| match device.kind {
+                MediaDeviceKind::Display(id) => Some(id),
+                _ => None,
+            })
+    }
+
+    /// Keep the cursor layer in step with the main source and the cursor settings.
+    fn sync_cursor_layer(&self) {
+        let effects = &self.settings.cursor_effects;
+        let transform = self
+            .main_source_label()
+            .map(|label| self.settings.video_transform(&label))
+            .unwrap_or_default();
+
+        let mut layers = self.frame_layers.lock().unwrap();
+        match self.main_display() {
+            Some(id) if effects.is_enabled() => {
+                let bounds = unsafe { CGDisplayBounds(id) };
+                match &mut layers.cursor {
+                    // Keep the running ripples when only the settings changed
+                    Some(cursor) => {
+                        cursor.effects = effects.clone();
+                        cursor.display = bounds;
+                        cursor.transform = transform;
+                    }
+                    None => {
+                        layers.cursor = Some(CursorLayer::new(effects.clone(), bounds, transform))
+                    }
+                }
+            }
+            _ => layers.cursor = None,
+        }
+    }
+
+    fn toggle_hotkey_masks(&mut self) {
+        self.masks_hotkey_on = !self.masks_hotkey_on;
+        self.sync_privacy_masks();
+    }
+
+    fn toggle_mask_drawing(&mut self) {
+        self.mask_drawing = !self.mask_drawing;
+        self.mask_drag = None;
+        if self.mask_drawing {
+            self.picker_active = false;
+            self.annotating = false;
+        }
+    }
+
+    fn toggle_annotating(&mut self) {
+        self.annotating = !self.annotating;
+        if self.annotating {
+            self.picker_active = false;
+            self.mask_drawing = false;
+            self.mask_drag = None;
+        } else {
+            self.frame_layers.lock().unwrap().annotations.finish();
+        }
+    }
+
+    fn sync_annotation_fade(&self) {
+        let settings = &self.settings.annotations;
+        self.frame_layers.lock().unwrap().annotations.fade_after = settings
+            .fade
+            .then(|| std::time::Duration::from_secs_f32(settings.fade_after));
+    }
+
+    /// Map a point on the preview to fractions of the frame, undoing a preview-only mirror.
+    fn preview_to_fraction(&self, pos: egui::Pos2) -> Option<[f32; 2]> {
+        let rect = self.main_preview_rect()?;
+        let relative = (pos - rect.min) / rect.size();
+        let x = if self.mirrors_preview_only(false) {
+            1.0 - relative.x
+        } else {
+            relative.x
+        };
+        Some([x, relative.y])
+    }
+
+    fn fraction_to_preview(&self, fraction: [f32; 2]) -> Option<egui::Pos2> {
+        let rect = self.main_preview_rect()?;
+        let x = if self.mirrors_preview_only(false) {
+            1.0 - fraction[0]
+        } else {
+            fraction[0]
+        };
+        Some(rect.min + egui::vec2(x, fraction[1]) * rect.size())
+    }
+
+    /// Store a mask drawn on the preview between two points.
+    fn add_privacy_mask(&mut self, a: egui::Pos2, b: egui::Pos2) {
+        let (Some(label), Some(a), Some(b)) = (
+            self.main_source_label(),
+            self.preview_to_fraction(a),
+            self.preview_to_fraction(b),
+        ) else {
+            return;
+        };
+        let mask = PrivacyMask::new(a, b, self.mask_effect);
+        if mask.rect[2] - mask.rect[0] < 0.005 || mask.rect[3] - mask.rect[1] < 0.005 {
+            return;
+        }
+        self.settings
+            .privacy_masks
+            .entry(label)
+            .or_default()
+            .push(mask);
+        self.sync_privacy_masks();
+    }
+
+    /// Whether the preview of the main source or the PiP is drawn mirrored
+    /// without the mirror being part of the frames.
+    fn mirrors_preview_only(&self, pip: bool) -> bool {
+        let label = if pip {
+            self.pip_source_label()
+        } else {
+            self.main_source_label()
+        };
+        label.is_some_and(|label| self.settings.video_transform(&label).mirrors_preview_only())
+    }
+
+    /// Adjustment and orientation controls for the main source or the PiP camera, applied live.
+    fn source_settings_ui(&mut self, ui: &mut egui::Ui, pip: bool) {
+        let label = if pip {
+            self.pip_source_label()
+        } else {
+            self.main_source_label()
+        };
+        let Some(label) = label else {
+            return;
+        };
+
+        let mut adjustments = self.settings.video_adjustments(&label);
+        egui::CollapsingHeader::new("Adjustments")
+            .id_salt(if pip {
+                "pip_adjustments"
+            } else {
+                "main_adjustments"
+            })
+            .show(ui, |ui| {
+                if video_adjust::controls(ui, &mut adjustments) {
+                    self.settings
+                        .video_adjustments
+                        .insert(label.clone(), adjustments.clone());
+                    self.apply_source_settings();
+                }
+            });
+
+        let mut transform = self.settings.video_transform(&label);
+        let can_rotate = !self.recording_state.is_active();
+        egui::CollapsingHeader::new("Orientation")
+            .id_salt(if pip {
+                "pip_orientation"
+            } else {
+                "main_orientation"
+            })
+            .show(ui, |ui| {
+                if video_transform::controls(ui, &mut transform, can_rotate) {
+                    self.settings
+                        .video_transforms
+                        .insert(label.clone(), transform);
+                    self.apply_source_settings();
+                }
+            });
+    }
+
+    fn switch_source(&mut self, device_idx: usize) {
+        // Stop the current pipeline first
+        if let Err(e) = self.pipeline.set_state(gst::State::Null) {
+            eprintln!("Error stopping pipeline: {:?}", e);
+        }
+
+        // Start the new pipeline with error handling
+        match setup_gstreamer(
+            device_idx,
+            self.frame_layers.clone(),
+            self.frame_data.clone(),
+            self.dimensions.clone(),
+        ) {
+            Ok(GstreamerSetup {
+                pipeline,
+                devices,
+                tx,
+            }) => {
+                self.pipeline = pipeline;
+                self.video_devices = devices;
+                self.update_dimensions_tx = tx;
+                self.current_device_idx = Some(device_idx);
+                self.keystrokes.set_pipeline(&self.pipeline);
+                self.apply_source_settings();
+
+                // A device can only feed one of the two videos
+                if self.show_pip {
+                    if let Some(conflict) = self.pip_conflict() {
+                        self.toggle_pip();
+                        self.pip_error = Some(conflict);
+                    }
+                }
+
+                // Update image size
+                let dims = self.dimensions.lock().unwrap();
+                self.image_size = egui::Vec2::new(dims.width as f32, dims.height as f32);
+                println!("Switched to device {}", device_idx);
+            }
+            Err(e) => {
+                eprintln!("Failed to start pipeline: {:?}", e);
+            }
+        }
+    }
+
+    fn switch_mic(&mut self, idx: usize) {
+        // Takes effect on the next recording, the selector is disabled while recording
+        self.current_mic_idx = Some(idx);
+        self.restart_audio_monitor();
+    }
+
+    /// Source element for the selected microphone.
+    fn mic_source(&self) -> String {
+        self.current_mic_idx
+            .and_then(|idx| self.audio_devices.get(idx))
+            .map(|device| device.setup_pipeline.clone())
+            .filter(|source| !source.is_empty())
+            .unwrap_or_else(|| "osxaudiosrc".to_string())
+    }
+
+    /// Raw, processed microphone audio ending at its `level` element.
+    fn mic_branch(&self) -> Option<String> {
+        if !self.is_mic_enabled {
+            return None;
+        }
+        Some(format!(
+            "{} ! audioconvert ! audioresample ! audio/x-raw,rate=44100,channels=2 ! {} ! \
+             level name=mic_level interval=50000000 post-messages=true",
+            self.mic_source(),
+            mic::processing_chain(&self.mic_settings, self.denoiser)
+        ))
+    }
+
+    /// Desktop audio ending at its `level` element, if enabled and available.
+    fn system_audio_branch(&self) -> Option<String> {
+        if !self.system_audio.enabled {
+            return None;
+        }
+        let device = self
+            .system_audio
+            .device_idx
+            .and_then(|idx| self.system_audio_devices.get(idx))?;
+        Some(system_audio::branch(
+            &self.system_audio,
+            &device.setup_pipeline,
+        ))
+    }
+
+    /// Keep a metering-only pipeline running while not recording, so the level
+    /// meter works before a recording starts. While recording, the recording
+    /// pipeline's own `level` element feeds the meter.
+    fn sync_audio_monitor(&mut self) {
+        let wanted = (self.mic_branch().is_some() || self.system_audio_branch().is_some())
+            && !self.recording_state.is_active();
+        if wanted && self.audio_monitor.is_none() && !self.audio_monitor_failed {
+            self.start_audio_monitor();
+        } else if !wanted && self.audio_monitor.is_some() {
+            self.stop_audio_monitor();
+        }
+
+        if let Some(bus) = self.audio_monitor.as_ref().and_then(|p| p.bus()) {
+            while let Some(msg) = bus.pop() {
+                if let gst::MessageView::Error(err) = msg.view() {
+                    eprintln!("Audio monitor error: {}", err.error());
+                    self.stop_audio_monitor();
+                    self.audio_monitor_failed = true;
+                    break;
+                }
+                self.audio_level.update_from_message(&msg, "mic_level");
+                self.system_audio_level
+                    .update_from_message(&msg, "system_level");
+            }
+        }
+    }
+
+    fn start_audio_monitor(&mut self) {
+        let pipeline_str = [self.mic_branch(), self.system_audio_branch()]
+            .into_iter()
+            .flatten()
+            .map(|branch| format!("{} ! fakesink sync=false", branch))
+            .collect::<Vec<_>>()
+            .join(" ");
+        let pipeline = match gst::parse::launch(&pipeline_str) {
+            Ok(element) => match element.downcast::<gst::Pipeline>() {
+                Ok(pipeline) => pipeline,
+                Err(_) => {
+                    eprintln!("Audio monitor is not a pipeline");
+                    self.audio_monitor_failed = true;
+                    return;
+                }
+            },
+            Err(e) => {
+                eprintln!("Failed to create audio monitor pipeline: {:?}", e);
+                self.audio_monitor_failed = true;
+                return;
+            }
+        };
+
+        mic::apply(&self.mic_settings, &pipeline);
+        system_audio::apply(&self.system_audio, &pipeline);
+        if let Err(e) = pipeline.set_state(gst::State::Playing) {
+            eprintln!("Failed to start audio monitor: {:?}", e);
+            let _ = pipeline.set_state(gst::State::Null);
+            self.audio_monitor_failed = true;
+            return;
+        }
+        self.audio_level.reset();
+        self.system_audio_level.reset();
+        self.audio_monitor = Some(pipeline);
+    }
+
+    /// Apply mic settings to whichever pipelines are currently capturing audio.
+    fn apply_mic_settings(&self) {
+        if let Some(pipeline) = &self.audio_monitor {
+            mic::apply(&self.mic_settings, pipeline);
+            system_audio::apply(&self.system_audio, pipeline);
+        }
+        if let Some(active) = &self.active_recording {
+            mic::apply(&self.mic_settings, &active.pipeline);
+            system_audio::apply(&self.system_audio, &active.pipeline);
+        }
+    }
+
+    fn toggle_mute(&mut self) {
+        self.mic_settings.muted = !self.mic_settings.muted;
+        self.apply_mic_settings();
+    }
+
+    fn stop_audio_monitor(&mut self) {
+        if let Some(pipeline) = self.audio_monitor.take() {
+            let _ = pipeline.set_state(gst::State::Null);
+        }
+        self.audio_level.reset();
+        self.system_audio_level.reset();
+    }
+
+    /// Stop the monitor and give it another try after the audio setup changed.
+    fn restart_audio_monitor(&mut self) {
+        self.stop_audio_monitor();
+        self.audio_monitor_failed = false;
+    }
+
+    fn current_device_label(&self) -> String {
+        self.current_device_idx
+            .and_then(|idx| self.video_devices.get(idx))
+            .map(|device| device.label.clone())
+            .unwrap_or_else(|| "FaceTime Camera".to_string())
+    }
+
+    fn current_mic_label(&self) -> String {
+        self.current_mic_idx
+            .and_then(|idx| self.audio_devices.get(idx))
+            .map(|device| device.label.clone())
+            .unwrap_or_else(|| "Default Microphone".to_string())
+    }
+
+    /// Device feeding the PiP, by default the first one that isn't the main source.
+    fn pip_device(&self) -> Option<usize> {
+        self.pip_device_idx.or_else(|| {
+            (0..self.video_devices.len()).find(|&idx| Some(idx) != self.current_device_idx)
+        })
+    }
+
+    /// Set when the PiP and the main video would capture the same device.
+    fn pip_conflict(&self) -> Option<String> {
+        let idx = self.pip_device()?;
+        (Some(idx) == self.current_device_idx).then(|| {
+            format!(
+                "{} is already the main video source",
+                self.video_devices[idx].label
+            )
+        })
+    }
+
+    fn switch_pip_source(&mut self, idx: usize) {
+        self.pip_device_idx = Some(idx);
+        if self.show_pip {
+            self.toggle_pip();
+            self.toggle_pip();
+        }
+    }
+
+    fn setup_pip_webcam(&mut self) -> Result<(), anyhow::Error> {
+        if let Some(conflict) = self.pip_conflict() {
+            return Err(anyhow::anyhow!(conflict));
+        }
+        let device = self
+            .pip_device()
+            .and_then(|idx| self.video_devices.get(idx))
+            .ok_or_else(|| anyhow::anyhow!("No video device for PiP"))?;
+
+        // Capture at the device's native resolution, only scale down for the overlay
+        let pipeline_str = format!(
+            "{} ! videoconvert ! videoflip name=flip ! videoscale ! capsfilter name=size ! \
+             videoconvert ! video/x-raw,format=RGBA ! \
+             alpha name=chroma method=set ! video/x-raw,format=RGBA ! \
+             videobalance name=balance ! gamma name=gamma ! \
+             appsink name=pip_sink sync=false drop=true max-buffers=1",
+            device.source_element()
+        );
+        println!("Using PiP pipeline: {}", pipeline_str);
+
+        let pipeline = gst::parse::launch(&pipeline_str)
+            .map_err(|e| anyhow::anyhow!("Failed to create PiP pipeline: {:?}", e))?
+            .downcast::<gst::Pipeline>()
+            .map_err(|_| anyhow::anyhow!("Failed to downcast to Pipeline"))?;
+
+        let appsink = pipeline
+            .by_name("pip_sink")
+            .ok_or(anyhow::anyhow!("Failed to find pip_sink"))?
+            .downcast::<gstreamer_app::AppSink>()
+            .map_err(|_| anyhow::anyhow!("Failed to cast to AppSink"))?;
+
+        // Configure appsink
+        appsink.set_max_buffers(1);
+        appsink.set_drop(true);
+        appsink.set_sync(false);
+
+        let frame_data = self.pip_frame_data.clone();
+        let dimensions = self.pip_dimensions.clone();
+        let style = self.pip_style.clone();
+        let styled = self.pip_styled.clone();
+        let key = self.pip_chroma_key.clone();
+
+        // Set up callbacks
+        appsink.set_callbacks(
+            gstreamer_app::AppSinkCallbacks::builder()
+                .new_sample(move |sink| {
+                    let sample = sink.pull_sample().map_err(|_| gst::FlowError::Error)?;
+
+                    // Update dimensions from caps
+                    if let Some(caps) = sample.caps() {
+                        if let Some(s) = caps.structure(0) {
+                            let mut dims = dimensions.lock().unwrap();
+                            dims.width = s.get::<i32>("width").unwrap_or(1280);
+                            dims.height = s.get::<i32>("height").unwrap_or(720);
+                        }
+                    }
+
+                    let buffer = sample.buffer().ok_or(gst::FlowError::Error)?;
+                    let map = buffer.map_readable().map_err(|_| gst::FlowError::Error)?;
+
+                    // The alpha element keyed the background, clean up the edges
+                    let mut frame = map.as_ref().to_vec();
+                    chroma_key::suppress_spill(&mut frame, &key.lock().unwrap());
+
+                    // Style once here, preview and recording share the result
+                    let (width, height) = {
+                        let dims = dimensions.lock().unwrap();
+                        (dims.width, dims.height)
+                    };
+                    if frame.len() == (width * height * 4) as usize {
+                        let style = style.lock().unwrap().clone();
+                        let styled_frame = pip_style::render(&frame, width, height, &style);
+                        *styled.lock().unwrap() = Some(styled_frame);
+                    }
+
+                    let mut data = frame_data.lock().unwrap();
+                    *data = Some(frame);
+
+                    Ok(gst::FlowSuccess::Ok)
+                })
+                .build(),
+        );
+
+        // Start the pipeline
+        pipeline.set_state(gst::State::Playing)?;
+
+        // The position follows the anchor, the capture size follows pip_video_size
+        self.pip_pipeline = Some(pipeline);
+        self.apply_source_settings();
+        self.pip_capture_width = 0;
+        self.update_pip_size();
+        self.show_pip = true;
+
+        Ok(())
+    }
+
+    /// Capture the PiP at the size it has in the video, so frames are never stretched.
+    fn update_pip_size(&mut self) {
+        let Some(caps_filter) = self
+            .pip_pipeline
+            .as_ref()
+            .and_then(|pipeline| pipeline.by_name("size"))
+        else {
+            return;
+        };
+
+        // The animator sizes the capture while the timeline moves the PiP
+        if self.pip_animated() {
+            self.pip_capture_width = 0;
+            return;
+        }
+
+        // The height follows the source aspect
+        let (camera_width, camera_height) = self.pip_camera_size();
+        let width = self.pip_style.lock().unwrap().capture_width(
+            self.pip_video_size.x,
+            camera_width,
+            camera_height,
+        );
+        if width == self.pip_capture_width {
+            return;
+        }
+        self.pip_capture_width = width;
+
+        let caps = gst::Caps::builder("video/x-raw")
+            .field("width", width)
+            .field("pixel-aspect-ratio", gst::Fraction::new(1, 1))
+            .build();
+        caps_filter.set_property("caps", &caps);
+    }
+
+    /// The current sources, PiP placement and overlays as a scene.
+    fn capture_scene(&self, name: String) -> Scene {
+        Scene {
+            name,
+            main_source: self.main_source_label(),
+            pip_source: self.show_pip.then(|| self.pip_source_label()).flatten(),
+            pip_anchor: self.pip_anchor,
+            pip_offset: [self.pip_offset.x, self.pip_offset.y],
+            pip_width: self.pip_video_size.x,
+            pip_margin: self.pip_margin,
+            overlays: self.settings.overlays.clone(),
+            layout: self.settings.split_layout.clone(),
+        }
+    }
+
+    /// Recall scene `idx`, crossfading from the current picture if set up to.
+    fn switch_scene(&mut self, idx: usize) {
+        let Some(scene) = self.settings.scenes.scenes.get(idx).cloned() else {
+            return;
+        };
+
+        if self.settings.scenes.transition == SceneTransition::Crossfade {
+            match self.compose_frame(true) {
+                Ok(frame) => {
+                    *self.crossfade_frame.lock().unwrap() = Some(frame);
+                    self.crossfade = Some(Crossfade::new(self.settings.scenes.fade_duration));
+                }
+                Err(e) => eprintln!("Cutting instead of crossfading: {:?}", e),
+            }
+        }
+
+        let find = |label: &Option<String>| {
+            let label = label.as_ref()?;
+            self.video_devices.iter().position(|d| &d.label == label)
+        };
+        let main = find(&scene.main_source).or(self.current_device_idx);
+        // A device can only feed one of the two videos
+        let pip = find(&scene.pip_source).filter(|idx| Some(*idx) != main);
+
+        // Stop the PiP first, the main video may be about to take its device
+        if self.show_pip && (pip.is_none() || pip != self.pip_device()) {
+            self.toggle_pip();
+        }
+        if let Some(main) = main.filter(|main| Some(*main) != self.current_device_idx) {
+            self.switch_source(main);
+        }
+        if let Some(pip) = pip.filter(|_| !self.show_pip) {
+            self.pip_device_idx = Some(pip);
+            self.toggle_pip();
+        }
+
+        self.pip_anchor = scene.pip_anchor;
+        self.pip_offset = egui::vec2(scene.pip_offset[0], scene.pip_offset[1]);
+        self.pip_video_size.x = scene.pip_width.max(PIP_MIN_WIDTH);
+        self.pip_margin = scene.pip_margin;
+        self.settings.overlays = scene.overlays;
+        self.settings.overlays.apply(&self.pipeline);
+        self.settings.split_layout = scene.layout;
+        self.active_scene = Some(idx);
+    }
+
+    /// Drop the crossfade once it has played out.
+    fn update_crossfade(&mut self) {
+        if self.crossfade.as_ref().is_some_and(|fade| fade.is_done()) {
+            self.crossfade = None;
+            *self.crossfade_frame.lock().unwrap() = None;
+        }
+    }
+
+    fn toggle_pip(&mut self) {
+        if self.show_pip {
+            // Stop PiP pipeline
+            if let Some(pipeline) = self.pip_pipeline.take() {
+                let _ = pipeline.set_state(gst::State::Null);
+            }
+            self.show_pip = false;
+        } else {
+            // Start PiP pipeline
+            self.pip_error = None;
+            if let Err(e) = self.setup_pip_webcam() {
+                eprintln!("Failed to start PiP webcam: {:?}", e);
+                self.pip_error = Some(e.to_string());
+            }
+        }
+    }
+}
+
+impl eframe::App for ScreenCapApp {
+    fn save(&mut self, storage: &mut dyn eframe::Storage) {
+        self.settings.save(storage);
+    }
+
+    fn on_exit(&mut self, _gl: Option<&eframe::glow::Context>) {
+        println!("On exit");
+        // Stop recording if active
+        if self.recording_state.is_active() {
+            self.finish_recording_blocking();
+        }
+
+        // Stop PiP pipeline if active
+        if let Some(pipeline) = self.pip_pipeline.take() {
+            if let Err(e) = pipeline.set_state(gst::State::Null) {
+                eprintln!("Error stopping PiP pipeline: {:?}", e);
+            }
+        }
+        self.stop_audio_monitor();
+
+        // Stop the main pipeline
+        if let Err(e) = self.pipeline.set_state(gst::State::Null) {
+            eprintln!("Error stopping pipeline: {:?}", e);
+        }
+
+        std::process::exit(0);
+    }
+
+    fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
+        // Advance the recording state machine from pipeline bus messages
+        self.poll_recording_bus();
+        let floating = !self.settings.split_layout.is_split();
+        if self.recording_state.is_active() && self.show_pip && floating {
+            self.apply_pip_timeline(self.recording_timer.elapsed().as_secs_f32());
+        }
+        self.sync_pip_animator();
+        self.update_crossfade();
+        self.sync_background(ctx);
+        self.update_recording_layout();
+        self.update_countdown();
+        self.sync_audio_monitor();
+
+        // Esc cancels a running countdown or leaves the color picker
+        if ctx.input(|i| i.key_pressed(egui::Key::Escape)) {
+            if self.recording_state == RecordingState::Countdown {
+                if let Err(e) = self.cancel_countdown() {
+                    eprintln!("Recording error: {:?}", e);
+                }
+            } else if self.picker_active {
+                self.toggle_picker();
+            } else if self.mask_drawing {
+                self.toggle_mask_drawing();
+            } else if self.annotating {
+                self.toggle_annotating();
+            }
+        }
+
+        // Add keyboard shortcuts
+        if ctx.input(|i| i.modifiers.command) {
+            if ctx.input(|i| i.key_pressed(egui::Key::R)) {
+                // Cmd+R to start/stop recording
+                self.toggle_recording();
+            }
+            if ctx.input(|i| i.key_pressed(egui::Key::P)) {
+                // Cmd+P to pause/resume recording
+                self.toggle_pause();
+            }
+            if ctx.input(|i| i.key_pressed(egui::Key::M)) {
+                // Cmd+M to mute/unmute the microphone
+                self.toggle_mute();
+            }
+            if ctx.input(|i| i.key_pressed(egui::Key::I)) {
+                // Cmd+I to toggle the color picker
+                self.toggle_picker();
+            }
+            if ctx.input(|i| i.key_pressed(egui::Key::B)) {
+                // Cmd+B to show/hide the hotkey privacy masks
+                self.toggle_hotkey_masks();
+            }
+            if ctx.input(|i| i.key_pressed(egui::Key::D)) {
+                // Cmd+D to draw on the video
+                self.toggle_annotating();
+            }
+            // Stroke shortcuts only while drawing, text fields keep their own undo
+            if self.annotating && !ctx.wants_keyboard_input() {
+                if ctx.input(|i| i.key_pressed(egui::Key::Z)) {
+                    // Cmd+Z to undo the last stroke
+                    self.frame_layers.lock().unwrap().annotations.undo();
+                }
+                if ctx.input(|i| i.key_pressed(egui::Key::K)) {
+                    // Cmd+K to clear all strokes
+                    self.frame_layers.lock().unwrap().annotations.clear();
+                }
+            }
+            if ctx.input(|i| i.key_pressed(egui::Key::S)) {
+                // Cmd+S to save a screenshot of the current frame
+                if let Err(e) = self.take_screenshot() {
+                    eprintln!("Failed to take screenshot: {:?}", e);
+                }
+            }
+        }
+
+        // Number keys switch scenes, unless they are typing into a field
+        if !ctx.wants_keyboard_input() {
+            let pressed = scenes::HOTKEYS
+                .iter()
+                .position(|key| ctx.input(|i| i.modifiers.is_none() && i.key_pressed(*key)));
+            if let Some(idx) = pressed {
+                self.switch_scene(idx);
+            }
+        }
+
+        // Set dark theme with custom colors
+        ctx.set_visuals(egui::Visuals::dark());
+
+        // Process frame data and update texture
+        if let Ok(frame_guard) = self.frame_data.lock() {
+            if let Some(buffer) = frame_guard.as_ref() {
+                let dims = self.dimensions.lock().unwrap();
+                let expected_size = (dims.width * dims.height * 4) as usize;
+
+                if buffer.len() == expected_size {
+                    let color_image = egui::ColorImage::from_rgba_unmultiplied(
+                        [dims.width as usize, dims.height as usize],
+                        buffer,
                     );
 
                     self.texture = Some(ctx.load_texture(
@@ -674,15 +2185,14 @@ impl eframe::App for ScreenCapApp {
 
         // Process PiP frame data and update texture
         if self.show_pip {
-            if let Ok(frame_guard) = self.pip_frame_data.lock() {
-                if let Some(buffer) = frame_guard.as_ref() {
-                    let dims = self.pip_dimensions.lock().unwrap();
-                    let expected_size = (dims.width * dims.height * 4) as usize;
+            if let Ok(styled_guard) = self.pip_styled.lock() {
+                if let Some(styled) = styled_guard.as_ref() {
+                    let expected_size = (styled.width * styled.height * 4) as usize;
 
-                    if buffer.len() == expected_size {
+                    if styled.data.len() == expected_size {
                         let color_image = egui::ColorImage::from_rgba_unmultiplied(
-                            [dims.width as usize, dims.height as usize],
-                            buffer,
+                            [styled.width as usize, styled.height as usize],
+                            &styled.data,
                         );
 
                         self.pip_texture = Some(ctx.load_texture(
@@ -696,14 +2206,16 @@ impl eframe::App for ScreenCapApp {
         }
 
         // Main video panel as background
+        let mut picked_pos = None;
+        let mut finished_mask = None;
+        let mut annotation_drag = None;
         egui::CentralPanel::default()
             .frame(egui::Frame::none().fill(egui::Color32::from_rgb(20, 20, 25)))
             .show(ctx, |ui| {
                 if let Some(texture) = &self.texture {
                     let available_size = ui.available_size();
-                    let dims = self.dimensions.lock().unwrap();
-                    let aspect_ratio = dims.width as f32 / dims.height as f32;
-                    drop(dims);
+                    let frame_size = self.frame_size();
+                    let aspect_ratio = frame_size.x / frame_size.y;
                     let mut size = available_size;
 
                     if available_size.x / available_size.y > aspect_ratio {
@@ -712,13 +2224,74 @@ impl eframe::App for ScreenCapApp {
                         size.y = available_size.x / aspect_ratio;
                     }
 
+                    // Split layouts show their background over the whole frame,
+                    // with the main video painted into its cell. A recording
+                    // letterboxes a source of another size on black
+                    let uv = preview_uv(self.mirrors_preview_only(false));
+                    let background = self.background_texture.as_ref();
+                    let main_rect = self.main_video_rect();
+                    let frame_rect = egui::Rect::from_min_size(egui::Pos2::ZERO, frame_size);
+                    let letterboxed = background.is_none() && main_rect != frame_rect;
                     ui.centered_and_justified(|ui| {
-                        ui.add(
-                            egui::Image::new(texture)
+                        let response = ui.add(
+                            egui::Image::new(background.unwrap_or(texture))
+                                .uv(if background.is_some() {
+                                    preview_uv(false)
+                                } else {
+                                    uv
+                                })
+                                .tint(if letterboxed {
+                                    egui::Color32::BLACK
+                                } else {
+                                    egui::Color32::WHITE
+                                })
                                 .fit_to_exact_size(size)
-                                .sense(egui::Sense::drag())
+                                .sense(egui::Sense::click_and_drag())
                                 .rounding(4.0),
                         );
+                        self.preview_rect = Some(response.rect);
+                        if background.is_some() || letterboxed {
+                            let rect =
+                                egui::emath::RectTransform::from_to(frame_rect, response.rect)
+                                    .transform_rect(main_rect);
+                            ui.painter()
+                                .image(texture.id(), rect, uv, egui::Color32::WHITE);
+                        }
+
+                        if self.picker_active {
+                            if response.hovered() {
+                                ui.ctx().set_cursor_icon(egui::CursorIcon::Crosshair);
+                            }
+                            if response.clicked() {
+                                picked_pos = response.interact_pointer_pos();
+                            }
+                        }
+
+                        // Drag out a new privacy mask
+                        if self.mask_drawing {
+                            if response.hovered() {
+                                ui.ctx().set_cursor_icon(egui::CursorIcon::Crosshair);
+                            }
+                            if let Some(pos) = response.interact_pointer_pos() {
+                                if response.drag_started() {
+                                    self.mask_drag = Some((pos, pos));
+                                } else if let Some((_, end)) = &mut self.mask_drag {
+                                    *end = pos;
+                                }
+                            }
+                            if response.drag_stopped() {
+                                if let Some((start, end)) = self.mask_drag.take() {
+                                    finished_mask = Some((start, end));
+                                }
+                            }
+                        }
+
+                        if self.annotating {
+                            if response.hovered() {
+                                ui.ctx().set_cursor_icon(egui::CursorIcon::Crosshair);
+                            }
+                            annotation_drag = Some(response);
+                        }
                     });
                 } else {
                     ui.centered_and_justified(|ui| {
@@ -729,7 +2302,97 @@ impl eframe::App for ScreenCapApp {
                         ));
                     });
                 }
-            });
+            });
+
+        if let Some((start, end)) = finished_mask {
+            self.add_privacy_mask(start, end);
+        }
+
+        // Strokes go straight into the frames as they are drawn
+        if let Some(response) = annotation_drag {
+            let at = response
+                .interact_pointer_pos()
+                .and_then(|pos| self.preview_to_fraction(pos));
+            let mut layers = self.frame_layers.lock().unwrap();
+            if let Some(at) = at {
+                if response.drag_started() {
+                    layers.annotations.begin(&self.settings.annotations, at);
+                } else if response.dragged() {
+                    layers.annotations.extend(at);
+                }
+            }
+            if response.drag_stopped() {
+                layers.annotations.finish();
+            }
+        }
+
+        // Outline what the zoomed recording shows
+        if let Some([left, top, right, bottom]) = self.auto_zoom.view() {
+            if let (Some(a), Some(b)) = (
+                self.fraction_to_preview([left, top]),
+                self.fraction_to_preview([right, bottom]),
+            ) {
+                ctx.layer_painter(egui::LayerId::new(
+                    egui::Order::Foreground,
+                    egui::Id::new("auto_zoom"),
+                ))
+                .rect_stroke(
+                    egui::Rect::from_two_pos(a, b),
+                    2.0,
+                    egui::Stroke::new(1.5, egui::Color32::from_rgb(120, 200, 255)),
+                );
+            }
+        }
+
+        // Outline the masks while drawing them
+        if self.mask_drawing {
+            let painter = ctx.layer_painter(egui::LayerId::new(
+                egui::Order::Foreground,
+                egui::Id::new("privacy_masks"),
+            ));
+            let masks = self
+                .main_source_label()
+                .and_then(|label| self.settings.privacy_masks.get(&label))
+                .cloned()
+                .unwrap_or_default();
+            for mask in &masks {
+                let [left, top, right, bottom] = mask.rect;
+                if let (Some(a), Some(b)) = (
+                    self.fraction_to_preview([left, top]),
+                    self.fraction_to_preview([right, bottom]),
+                ) {
+                    painter.rect_stroke(
+                        egui::Rect::from_two_pos(a, b),
+                        2.0,
+                        egui::Stroke::new(1.5, egui::Color32::from_rgb(255, 200, 80)),
+                    );
+                }
+            }
+            if let Some((start, end)) = self.mask_drag {
+                painter.rect_stroke(
+                    egui::Rect::from_two_pos(start, end),
+                    2.0,
+                    egui::Stroke::new(2.0, egui::Color32::WHITE),
+                );
+            }
+        }
+
+        // Color picker loupe follows the pointer over the preview
+        if self.picker_active {
+            if let Some(pointer) = ctx.pointer_hover_pos() {
+                if let Some(source) = self.preview_to_source(pointer) {
+                    color_picker::show_loupe(ctx, pointer, source, |x, y| self.get_pixel(x, y));
+                }
+            }
+            if let Some(pixel) = picked_pos
+                .and_then(|pos| self.preview_to_source(pos))
+                .and_then(|(x, y)| self.get_pixel(x, y))
+            {
+                let hex = color_picker::hex(pixel);
+                ctx.copy_text(hex.clone());
+                self.picker_last_copied = Some(hex);
+            }
+        }
 
         // Settings button in the corner with controls
         egui::Window::new("settings_collapsed")
@@ -759,16 +2422,18 @@ impl eframe::App for ScreenCapApp {
                     }
 
                     // Record button
+                    let is_active = self.recording_state.is_active();
                     if ui
-                        .add(
+                        .add_enabled(
+                            self.recording_state != RecordingState::Finalizing,
                             egui::Button::new(
-                                egui::RichText::new(if self.is_recording {
+                                egui::RichText::new(if is_active {
                                     RECORD_ON_ICON
                                 } else {
                                     RECORD_OFF_ICON
                                 })
                                 .font(FontId::proportional(18.0))
-                                .color(if self.is_recording {
+                                .color(if is_active {
                                     egui::Color32::from_rgb(255, 80, 80)
                                 } else {
                                     egui::Color32::LIGHT_GRAY
@@ -778,18 +2443,178 @@ impl eframe::App for ScreenCapApp {
                         )
                         .clicked()
                     {
-                        if self.is_recording {
-                            self.stop_recording();
-                        } else {
-                            match self.start_recording() {
-                                Ok(_) => {
-                                    self.is_recording = true;
-                                }
-                                Err(e) => {
-                                    println!("Failed to start recording: {:?}", e);
-                                }
+                        self.toggle_recording();
+                    }
+
+                    // Mute button
+                    if self.is_mic_enabled
+                        && ui
+                            .add(
+                                egui::Button::new(
+                                    egui::RichText::new(if self.mic_settings.muted {
+                                        MIC_OFF_ICON
+                                    } else {
+                                        MIC_ON_ICON
+                                    })
+                                    .font(FontId::proportional(18.0))
+                                    .color(
+                                        if self.mic_settings.muted {
+                                            egui::Color32::from_rgb(255, 80, 80)
+                                        } else {
+                                            egui::Color32::LIGHT_GRAY
+                                        },
+                                    ),
+                                )
+                                .frame(false),
+                            )
+                            .on_hover_text("Mute microphone (Cmd+M)")
+                            .clicked()
+                    {
+                        self.toggle_mute();
+                    }
+
+                    // Pause button and recording status
+                    match &self.recording_state {
+                        RecordingState::Recording | RecordingState::Paused => {
+                            let is_paused = self.recording_state == RecordingState::Paused;
+                            if ui
+                                .add(
+                                    egui::Button::new(
+                                        egui::RichText::new(if is_paused {
+                                            RESUME_ICON
+                                        } else {
+                                            PAUSE_ICON
+                                        })
+                                        .font(FontId::proportional(18.0)),
+                                    )
+                                    .frame(false),
+                                )
+                                .clicked()
+                            {
+                                self.toggle_pause();
+                            }
+                            ui.label(
+                                egui::RichText::new(format_duration(
+                                    self.recording_timer.elapsed(),
+                                ))
+                                .monospace()
+                                .color(if is_paused {
+                                    egui::Color32::LIGHT_GRAY
+                                } else {
+                                    egui::Color32::from_rgb(255, 80, 80)
+                                }),
+                            );
+                            if self.is_mic_enabled {
+                                audio_meter::vu_meter(ui, &self.audio_level, egui::vec2(40.0, 6.0));
                             }
                         }
+                        RecordingState::Starting => {
+                            ui.spinner();
+                        }
+                        RecordingState::Finalizing => {
+                            ui.spinner();
+                            ui.label(egui::RichText::new("Saving").size(12.0));
+                        }
+                        RecordingState::Failed(msg) => {
+                            ui.label(
+                                egui::RichText::new("Failed")
+                                    .size(12.0)
+                                    .color(egui::Color32::from_rgb(255, 80, 80)),
+                            )
+                            .on_hover_text(msg.as_str());
+                        }
+                        RecordingState::Countdown => {
+                            ui.label(
+                                egui::RichText::new("Esc to cancel")
+                                    .size(12.0)
+                                    .color(egui::Color32::LIGHT_GRAY),
+                            );
+                        }
+                        RecordingState::Idle => {}
+                    }
+
+                    // Screenshot button
+                    if ui
+                        .add(
+                            egui::Button::new(
+                                egui::RichText::new(SCREENSHOT_ICON)
+                                    .font(FontId::proportional(18.0)),
+                            )
+                            .frame(false),
+                        )
+                        .on_hover_text("Screenshot (Cmd+S)")
+                        .clicked()
+                    {
+                        if let Err(e) = self.take_screenshot() {
+                            eprintln!("Failed to take screenshot: {:?}", e);
+                        }
+                    }
+
+                    // Color picker button
+                    if ui
+                        .add(
+                            egui::Button::new(
+                                egui::RichText::new(PICKER_ICON)
+                                    .font(FontId::proportional(18.0))
+                                    .color(if self.picker_active {
+                                        egui::Color32::from_rgb(100, 180, 255)
+                                    } else {
+                                        egui::Color32::LIGHT_GRAY
+                                    }),
+                            )
+                            .frame(false),
+                        )
+                        .on_hover_text("Color picker (Cmd+I)")
+                        .clicked()
+                    {
+                        self.toggle_picker();
+                    }
+                    if let Some(copied) = &self.picker_last_copied {
+                        ui.label(
+                            egui::RichText::new(format!("Copied {}", copied))
+                                .size(12.0)
+                                .monospace(),
+                        );
+                    }
+
+                    // Privacy mask drawing button
+                    if ui
+                        .add(
+                            egui::Button::new(
+                                egui::RichText::new(MASK_ICON)
+                                    .font(FontId::proportional(18.0))
+                                    .color(if self.mask_drawing {
+                                        egui::Color32::from_rgb(255, 200, 80)
+                                    } else {
+                                        egui::Color32::LIGHT_GRAY
+                                    }),
+                            )
+                            .frame(false),
+                        )
+                        .on_hover_text("Draw privacy masks")
+                        .clicked()
+                    {
+                        self.toggle_mask_drawing();
+                    }
+
+                    // Live annotation button
+                    if ui
+                        .add(
+                            egui::Button::new(
+                                egui::RichText::new(ANNOTATE_ICON)
+                                    .font(FontId::proportional(18.0))
+                                    .color(if self.annotating {
+                                        egui::Color32::from_rgb(255, 200, 80)
+                                    } else {
+                                        egui::Color32::LIGHT_GRAY
+                                    }),
+                            )
+                            .frame(false),
+                        )
+                        .on_hover_text("Draw on the video (Cmd+D)")
+                        .clicked()
+                    {
+                        self.toggle_annotating();
                     }
 
                     // Fullscreen button
@@ -842,103 +2667,538 @@ impl eframe::App for ScreenCapApp {
                 .show(ctx, |ui| {
                     ui.add_space(4.0);
 
-                    // Source selection with modern style
+                    // Source selection with modern style
+                    ui.label(
+                        egui::RichText::new("Video Source")
+                            .size(13.0)
+                            .color(egui::Color32::from_rgb(180, 180, 180)),
+                    );
+
+                    let mut selected_video_src_idx = None;
+                    ui.horizontal(|ui| {
+                        let current_label = self
+                            .current_device_idx
+                            .and_then(|idx| self.video_devices.get(idx))
+                            .map(|device| device.label.as_str())
+                            .unwrap_or("Default");
+
+                        egui::ComboBox::from_id_salt("source_select")
+                            .selected_text(current_label)
+                            .width(ui.available_width() - 40.0)
+                            .show_ui(ui, |ui| {
+                                for (idx, device) in self.video_devices.iter().enumerate() {
+                                    let selected = Some(idx) == self.current_device_idx;
+                                    if ui.selectable_label(selected, &device.label).clicked()
+                                        && !selected
+                                    {
+                                        selected_video_src_idx = Some(idx);
+                                    }
+                                }
+                            });
+                        ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                            let is_on = self.current_device_idx == Some(0);
+                            ui.add(
+                                egui::Button::new(
+                                    egui::RichText::new(if is_on { "On" } else { "Off" })
+                                        .size(12.0)
+                                        .color(if is_on {
+                                            egui::Color32::from_rgb(100, 255, 100)
+                                        } else {
+                                            egui::Color32::LIGHT_GRAY
+                                        }),
+                                )
+                                .frame(false),
+                            );
+                        });
+                    });
+                    self.source_settings_ui(ui, false);
+
+                    ui.add_space(12.0);
+
+                    // Audio selection with modern style
+                    ui.label(
+                        egui::RichText::new("Audio Source")
+                            .size(13.0)
+                            .color(egui::Color32::from_rgb(180, 180, 180)),
+                    );
+
+                    let mut selected_mic_idx = None;
+                    let mut mic_toggled = false;
+                    ui.horizontal(|ui| {
+                        let current_label = self
+                            .current_mic_idx
+                            .and_then(|idx| self.audio_devices.get(idx))
+                            .map(|device| device.label.as_str())
+                            .unwrap_or("Default");
+
+                        // Audio devices can only change between recordings, the
+                        // input level of the selected microphone sits next to them
+                        let is_idle = !self.recording_state.is_active();
+                        let meter_width = if self.is_mic_enabled { 60.0 } else { 0.0 };
+                        ui.add_enabled_ui(is_idle, |ui| {
+                            egui::ComboBox::from_id_salt("mic_select")
+                                .selected_text(current_label)
+                                .width(ui.available_width() - 40.0 - meter_width)
+                                .show_ui(ui, |ui| {
+                                    for (idx, device) in self.audio_devices.iter().enumerate() {
+                                        let selected = Some(idx) == self.current_mic_idx;
+                                        if ui.selectable_label(selected, &device.label).clicked()
+                                            && !selected
+                                        {
+                                            selected_mic_idx = Some(idx);
+                                        }
+                                    }
+                                });
+                        });
+                        if self.is_mic_enabled {
+                            audio_meter::vu_meter(
+                                ui,
+                                &self.audio_level,
+                                egui::vec2(meter_width, 8.0),
+                            );
+                        }
+
+                        ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                            if ui
+                                .add_enabled(
+                                    is_idle,
+                                    egui::Checkbox::without_text(&mut self.is_mic_enabled),
+                                )
+                                .changed()
+                            {
+                                mic_toggled = true;
+                            }
+                        });
+                    });
+
+                    if self.is_mic_enabled {
+                        // Gain, gate and suppression apply live to monitor and recording
+                        let before = self.mic_settings.clone();
+                        ui.add_space(4.0);
+                        ui.horizontal(|ui| {
+                            ui.add(
+                                egui::Slider::new(&mut self.mic_settings.gain, 0.0..=4.0)
+                                    .text("Gain")
+                                    .custom_formatter(|v, _| format!("{:.0}%", v * 100.0)),
+                            );
+                            ui.toggle_value(&mut self.mic_settings.muted, "Mute");
+                        });
+                        ui.horizontal(|ui| {
+                            ui.checkbox(&mut self.mic_settings.gate_enabled, "Noise gate");
+                            ui.add_enabled(
+                                self.mic_settings.gate_enabled,
+                                egui::Slider::new(
+                                    &mut self.mic_settings.gate_threshold_db,
+                                    -80.0..=-10.0,
+                                )
+                                .suffix(" dB"),
+                            );
+                        });
+                        if let Some(denoiser) = self.denoiser {
+                            let is_idle = !self.recording_state.is_active();
+                            ui.horizontal(|ui| {
+                                // Inserting the denoiser changes the pipeline, so only
+                                // its strength can change during a recording
+                                ui.add_enabled(
+                                    is_idle,
+                                    egui::Checkbox::new(
+                                        &mut self.mic_settings.noise_suppression,
+                                        format!("Noise suppression ({})", denoiser.label()),
+                                    ),
+                                );
+                                ui.add_enabled(
+                                    self.mic_settings.noise_suppression,
+                                    egui::Slider::new(
+                                        &mut self.mic_settings.suppression_strength,
+                                        0.0..=1.0,
+                                    )
+                                    .show_value(false),
+                                );
+                            });
+                        }
+
+                        if self.mic_settings.noise_suppression != before.noise_suppression {
+                            self.stop_audio_monitor();
+                        } else if self.mic_settings != before {
+                            self.apply_mic_settings();
+                        }
+                    }
+
+                    // Desktop audio, mixed with the mic or kept as its own track
+                    ui.add_space(12.0);
+                    ui.label(
+                        egui::RichText::new("System Audio")
+                            .size(13.0)
+                            .color(egui::Color32::from_rgb(180, 180, 180)),
+                    );
+
+                    let before = self.system_audio.clone();
+                    let is_idle = !self.recording_state.is_active();
+                    if self.system_audio_devices.is_empty() {
+                        ui.label(
+                            egui::RichText::new(
+                                "No loopback device found, install one such as BlackHole",
+                            )
+                            .size(12.0)
+                            .color(egui::Color32::GRAY),
+                        );
+                    } else {
+                        ui.horizontal(|ui| {
+                            let current_label = self
+                                .system_audio
+                                .device_idx
+                                .and_then(|idx| self.system_audio_devices.get(idx))
+                                .map(|device| device.label.as_str())
+                                .unwrap_or("None");
+
+                            ui.add_enabled_ui(is_idle, |ui| {
+                                egui::ComboBox::from_id_salt("system_audio_select")
+                                    .selected_text(current_label)
+                                    .width(ui.available_width() - 40.0)
+                                    .show_ui(ui, |ui| {
+                                        for (idx, device) in
+                                            self.system_audio_devices.iter().enumerate()
+                                        {
+                                            ui.selectable_value(
+                                                &mut self.system_audio.device_idx,
+                                                Some(idx),
+                                                &device.label,
+                                            );
+                                        }
+                                    });
+                            });
+
+                            ui.with_layout(
+                                egui::Layout::right_to_left(egui::Align::Center),
+                                |ui| {
+                                    ui.add_enabled(
+                                        is_idle,
+                                        egui::Checkbox::without_text(
+                                            &mut self.system_audio.enabled,
+                                        ),
+                                    );
+                                },
+                            );
+                        });
+
+                        if self.system_audio.enabled {
+                            ui.add_space(4.0);
+                            let width = ui.available_width() - 40.0;
+                            audio_meter::vu_meter(
+                                ui,
+                                &self.system_audio_level,
+                                egui::vec2(width, 8.0),
+                            );
+                            ui.add_space(4.0);
+                            ui.horizontal(|ui| {
+                                ui.add(
+                                    egui::Slider::new(&mut self.system_audio.volume, 0.0..=2.0)
+                                        .text("Volume")
+                                        .custom_formatter(|v, _| format!("{:.0}%", v * 100.0)),
+                                );
+                                ui.toggle_value(&mut self.system_audio.muted, "Mute");
+                            });
+                        }
+                    }
+
+                    // Track layout only matters once there is more than one audio source
+                    if self.system_audio.enabled && self.is_mic_enabled {
+                        ui.add_space(4.0);
+                        ui.add_enabled_ui(is_idle, |ui| {
+                            ui.horizontal(|ui| {
+                                ui.label(egui::RichText::new("Audio tracks").size(12.0));
+                                egui::ComboBox::from_id_salt("audio_track_layout")
+                                    .selected_text(self.audio_track_layout.label())
+                                    .show_ui(ui, |ui| {
+                                        for layout in AudioTrackLayout::ALL {
+                                            ui.selectable_value(
+                                                &mut self.audio_track_layout,
+                                                layout,
+                                                layout.label(),
+                                            );
+                                        }
+                                    });
+                                ui.label(egui::RichText::new("Language").size(12.0));
+                                let valid = audio_tracks::is_language_code(
+                                    self.audio_track_language.trim(),
+                                );
+                                ui.add(
+                                    egui::TextEdit::singleline(&mut self.audio_track_language)
+                                        .desired_width(32.0)
+                                        .text_color_opt(
+                                            (!valid)
+                                                .then_some(egui::Color32::from_rgb(255, 120, 120)),
+                                        ),
+                                )
+                                .on_hover_text("ISO 639 code such as en or deu, und when invalid");
+                            });
+                        });
+                    }
+
+                    if self.system_audio.enabled != before.enabled
+                        || self.system_audio.device_idx != before.device_idx
+                    {
+                        self.restart_audio_monitor();
+                    } else if self.system_audio != before {
+                        self.apply_mic_settings();
+                    }
+
+                    // Handle source switching outside the UI closure
+                    if let Some(idx) = selected_video_src_idx {
+                        self.switch_source(idx);
+                    }
+                    if let Some(idx) = selected_mic_idx {
+                        self.switch_mic(idx);
+                    }
+                    if mic_toggled {
+                        self.restart_audio_monitor();
+                    }
+
+                    // Recording mode, audio only skips video capture entirely
+                    ui.add_space(12.0);
+                    ui.label(
+                        egui::RichText::new("Recording Mode")
+                            .size(13.0)
+                            .color(egui::Color32::from_rgb(180, 180, 180)),
+                    );
+                    ui.add_enabled_ui(!self.recording_state.is_active(), |ui| {
+                        ui.horizontal(|ui| {
+                            ui.selectable_value(&mut self.audio_only, false, "Video");
+                            ui.selectable_value(&mut self.audio_only, true, "Audio only");
+                            if self.audio_only {
+                                egui::ComboBox::from_id_salt("audio_format")
+                                    .selected_text(self.audio_format.label())
+                                    .show_ui(ui, |ui| {
+                                        for format in AudioFormat::ALL {
+                                            ui.selectable_value(
+                                                &mut self.audio_format,
+                                                format,
+                                                format.label(),
+                                            );
+                                        }
+                                    });
+                            }
+                        });
+                    });
+
+                    // Screenshot options
+                    ui.add_space(12.0);
+                    ui.label(
+                        egui::RichText::new("Screenshots")
+                            .size(13.0)
+                            .color(egui::Color32::from_rgb(180, 180, 180)),
+                    );
+                    ui.horizontal(|ui| {
+                        egui::ComboBox::from_id_salt("screenshot_format")
+                            .selected_text(self.screenshot_format.label())
+                            .show_ui(ui, |ui| {
+                                for format in ScreenshotFormat::ALL {
+                                    ui.selectable_value(
+                                        &mut self.screenshot_format,
+                                        format,
+                                        format.label(),
+                                    );
+                                }
+                            });
+                        ui.checkbox(&mut self.screenshot_include_pip, "Include PiP");
+                        ui.checkbox(&mut self.screenshot_annotate, "Annotate");
+                    });
+
+                    // Named setups, switched live with the number keys
+                    ui.add_space(12.0);
+                    ui.label(
+                        egui::RichText::new("Scenes")
+                            .size(13.0)
+                            .color(egui::Color32::from_rgb(180, 180, 180)),
+                    );
+                    match scenes::controls(ui, &mut self.settings.scenes, self.active_scene) {
+                        Some(SceneAction::Switch(idx)) => self.switch_scene(idx),
+                        Some(SceneAction::Capture(Some(idx))) => {
+                            let name = self.settings.scenes.scenes[idx].name.clone();
+                            self.settings.scenes.scenes[idx] = self.capture_scene(name);
+                        }
+                        Some(SceneAction::Capture(None)) => {
+                            let name = format!("Scene {}", self.settings.scenes.scenes.len() + 1);
+                            let scene = self.capture_scene(name);
+                            self.settings.scenes.scenes.push(scene);
+                            self.active_scene = Some(self.settings.scenes.scenes.len() - 1);
+                        }
+                        Some(SceneAction::Remove(idx)) => {
+                            self.settings.scenes.scenes.remove(idx);
+                            self.active_scene = None;
+                        }
+                        None => {}
+                    }
+
+                    // Title, timestamp and logo burned into the main video
+                    ui.add_space(12.0);
+                    ui.label(
+                        egui::RichText::new("Overlays")
+                            .size(13.0)
+                            .color(egui::Color32::from_rgb(180, 180, 180)),
+                    );
+                    if overlays::controls(ui, &mut self.settings.overlays) {
+                        self.settings.overlays.apply(&self.pipeline);
+                    }
+
+                    // Live drawing over the main video
+                    ui.add_space(12.0);
+                    ui.label(
+                        egui::RichText::new("Annotations")
+                            .size(13.0)
+                            .color(egui::Color32::from_rgb(180, 180, 180)),
+                    );
+                    ui.horizontal(|ui| {
+                        if ui
+                            .selectable_label(self.annotating, "Draw")
+                            .on_hover_text("Cmd+D")
+                            .clicked()
+                        {
+                            self.toggle_annotating();
+                        }
+                        let has_strokes = !self.frame_layers.lock().unwrap().annotations.is_empty();
+                        if ui
+                            .add_enabled(has_strokes, egui::Button::new("Undo"))
+                            .on_hover_text("Cmd+Z")
+                            .clicked()
+                        {
+                            self.frame_layers.lock().unwrap().annotations.undo();
+                        }
+                        if ui
+                            .add_enabled(has_strokes, egui::Button::new("Clear"))
+                            .on_hover_text("Cmd+K")
+                            .clicked()
+                        {
+                            self.frame_layers.lock().unwrap().annotations.clear();
+                        }
+                    });
+                    if annotations::controls(ui, &mut self.settings.annotations) {
+                        self.sync_annotation_fade();
+                    }
+
+                    // Pointer effects drawn into screen captures
+                    ui.add_space(12.0);
                     ui.label(
-                        egui::RichText::new("Video Source")
+                        egui::RichText::new("Cursor")
                             .size(13.0)
                             .color(egui::Color32::from_rgb(180, 180, 180)),
                     );
+                    if cursor_fx::controls(ui, &mut self.settings.cursor_effects) {
+                        self.sync_cursor_layer();
+                    }
 
-                    let mut selected_video_src_idx = None;
-                    ui.horizontal(|ui| {
-                        let current_label = self
-                            .current_device_idx
-                            .and_then(|idx| self.video_devices.get(idx))
-                            .map(|device| device.label.as_str())
-                            .unwrap_or("Default");
-
-                        egui::ComboBox::from_id_salt("source_select")
-                            .selected_text(current_label)
-                            .width(ui.available_width() - 40.0)
-                            .show_ui(ui, |ui| {
-                                for (idx, device) in self.video_devices.iter().enumerate() {
-                                    let selected = Some(idx) == self.current_device_idx;
-                                    if ui.selectable_label(selected, &device.label).clicked()
-                                        && !selected
-                                    {
-                                        selected_video_src_idx = Some(idx);
-                                    }
-                                }
-                            });
-                        ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
-                            let is_on = self.current_device_idx == Some(0);
-                            ui.add(
-                                egui::Button::new(
-                                    egui::RichText::new(if is_on { "On" } else { "Off" })
-                                        .size(12.0)
-                                        .color(if is_on {
-                                            egui::Color32::from_rgb(100, 255, 100)
-                                        } else {
-                                            egui::Color32::LIGHT_GRAY
-                                        }),
-                                )
-                                .frame(false),
-                            );
-                        });
-                    });
-
+                    // Zoom and pan of recorded screen captures
                     ui.add_space(12.0);
+                    ui.label(
+                        egui::RichText::new("Zoom")
+                            .size(13.0)
+                            .color(egui::Color32::from_rgb(180, 180, 180)),
+                    );
+                    if auto_zoom::controls(ui, &mut self.settings.auto_zoom) {
+                        self.auto_zoom.set_settings(&self.settings.auto_zoom);
+                    }
 
-                    // Audio selection with modern style
+                    // Key combos for tutorials
+                    ui.add_space(12.0);
                     ui.label(
-                        egui::RichText::new("Audio Source")
+                        egui::RichText::new("Keystrokes")
                             .size(13.0)
                             .color(egui::Color32::from_rgb(180, 180, 180)),
                     );
+                    if keystrokes::controls(ui, &mut self.settings.keystrokes) {
+                        self.keystrokes.set_settings(&self.settings.keystrokes);
+                    }
 
-                    let mut selected_mic_idx = None;
+                    // Privacy masks of the main source
+                    ui.add_space(12.0);
+                    ui.label(
+                        egui::RichText::new("Privacy Masks")
+                            .size(13.0)
+                            .color(egui::Color32::from_rgb(180, 180, 180)),
+                    );
                     ui.horizontal(|ui| {
-                        let current_label = self
-                            .current_mic_idx
-                            .and_then(|idx| self.audio_devices.get(idx))
-                            .map(|device| device.label.as_str())
-                            .unwrap_or("Default");
-
-                        egui::ComboBox::from_id_salt("mic_select")
-                            .selected_text(current_label)
-                            .width(ui.available_width() - 40.0)
+                        if ui
+                            .selectable_label(self.mask_drawing, "Draw")
+                            .on_hover_text("Drag on the preview to add a mask")
+                            .clicked()
+                        {
+                            self.toggle_mask_drawing();
+                        }
+                        egui::ComboBox::from_id_salt("mask_effect")
+                            .selected_text(self.mask_effect.label())
                             .show_ui(ui, |ui| {
-                                for (idx, device) in self.audio_devices.iter().enumerate() {
-                                    let selected = Some(idx) == self.current_mic_idx;
-                                    if ui.selectable_label(selected, &device.label).clicked()
-                                        && !selected
-                                    {
-                                        selected_mic_idx = Some(idx);
-                                    }
+                                for effect in MaskEffect::ALL {
+                                    ui.selectable_value(
+                                        &mut self.mask_effect,
+                                        effect,
+                                        effect.label(),
+                                    );
                                 }
                             });
-
-                        ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
-                            let mut is_enabled = self.is_mic_enabled;
-                            ui.checkbox(&mut is_enabled, "");
-                            if is_enabled != self.is_mic_enabled {
-                                self.is_mic_enabled = is_enabled;
-                                if self.is_recording {
-                                    self.stop_recording();
-                                    self.start_recording();
-                                }
-                            }
-                        });
+                        if ui
+                            .selectable_label(self.masks_hotkey_on, "Hotkey masks on")
+                            .on_hover_text("Cmd+B")
+                            .clicked()
+                        {
+                            self.toggle_hotkey_masks();
+                        }
                     });
-
-                    // Handle source switching outside the UI closure
-                    if let Some(idx) = selected_video_src_idx {
-                        self.switch_source(idx);
-                    }
-                    if let Some(idx) = selected_mic_idx {
-                        self.switch_mic(idx);
+                    if let Some(masks) = self
+                        .main_source_label()
+                        .and_then(|label| self.settings.privacy_masks.get_mut(&label))
+                    {
+                        let mut changed = false;
+                        let mut remove = None;
+                        for (idx, mask) in masks.iter_mut().enumerate() {
+                            ui.horizontal(|ui| {
+                                ui.label(format!("Mask {}", idx + 1));
+                                egui::ComboBox::from_id_salt(("mask_effect", idx))
+                                    .selected_text(mask.effect.label())
+                                    .show_ui(ui, |ui| {
+                                        for effect in MaskEffect::ALL {
+                                            changed |= ui
+                                                .selectable_value(
+                                                    &mut mask.effect,
+                                                    effect,
+                                                    effect.label(),
+                                                )
+                                                .changed();
+                                        }
+                                    });
+                                changed |= ui
+                                    .checkbox(&mut mask.hotkey, "Hotkey")
+                                    .on_hover_text("Only hide this region while toggled on")
+                                    .changed();
+                                if ui.small_button("Remove").clicked() {
+                                    remove = Some(idx);
+                                }
+                            });
+                        }
+                        if let Some(idx) = remove {
+                            masks.remove(idx);
+                            changed = true;
+                        }
+                        if changed {
+                            self.sync_privacy_masks();
+                        }
                     }
 
+                    // Countdown before recording starts
+                    ui.add_space(12.0);
+                    ui.label(
+                        egui::RichText::new("Countdown")
+                            .size(13.0)
+                            .color(egui::Color32::from_rgb(180, 180, 180)),
+                    );
+                    ui.horizontal(|ui| {
+                        ui.add(
+                            egui::Slider::new(&mut self.countdown_secs, 0..=10)
+                                .suffix(" s")
+                                .text("0 = off"),
+                        );
+                        ui.checkbox(&mut self.countdown_beep, "Beep");
+                    });
+
                     // PiP toggle in settings
                     ui.add_space(12.0);
                     ui.horizontal(|ui| {
@@ -954,113 +3214,431 @@ impl eframe::App for ScreenCapApp {
                             self.toggle_pip();
                         }
                     });
+
+                    let mut selected_pip_src_idx = None;
+                    let pip_label = self
+                        .pip_device()
+                        .and_then(|idx| self.video_devices.get(idx))
+                        .map(|device| device.label.as_str())
+                        .unwrap_or("None");
+                    egui::ComboBox::from_id_salt("pip_source_select")
+                        .selected_text(pip_label)
+                        .width(ui.available_width() - 40.0)
+                        .show_ui(ui, |ui| {
+                            for (idx, device) in self.video_devices.iter().enumerate() {
+                                let selected = Some(idx) == self.pip_device();
+                                let in_use = Some(idx) == self.current_device_idx;
+                                let label = if in_use {
+                                    format!("{} (main source)", device.label)
+                                } else {
+                                    device.label.clone()
+                                };
+                                if ui
+                                    .add_enabled(
+                                        !in_use,
+                                        egui::SelectableLabel::new(selected, label),
+                                    )
+                                    .clicked()
+                                    && !selected
+                                {
+                                    selected_pip_src_idx = Some(idx);
+                                }
+                            }
+                        });
+                    if let Some(idx) = selected_pip_src_idx {
+                        self.switch_pip_source(idx);
+                    }
+                    if let Some(error) = &self.pip_error {
+                        ui.label(
+                            egui::RichText::new(error)
+                                .size(11.0)
+                                .color(egui::Color32::from_rgb(255, 120, 120)),
+                        );
+                    }
+
+                    // Floating PiP or a split with the main video
+                    split_layout::controls(ui, &mut self.settings.split_layout);
+
+                    // Placement, shape, border and shadow of the webcam overlay
+                    if self.show_pip {
+                        if !self.settings.split_layout.is_split() {
+                            ui.horizontal_wrapped(|ui| {
+                                ui.label("Position");
+                                for anchor in PipAnchor::ALL {
+                                    ui.selectable_value(
+                                        &mut self.pip_anchor,
+                                        Some(anchor),
+                                        anchor.label(),
+                                    );
+                                }
+                                ui.selectable_value(&mut self.pip_anchor, None, "Free");
+                            });
+                            ui.add(
+                                egui::Slider::new(&mut self.pip_margin, 0.0..=200.0)
+                                    .suffix(" px")
+                                    .text("Margin"),
+                            );
+                        }
+
+                        self.source_settings_ui(ui, true);
+
+                        if ui
+                            .selectable_label(self.show_timeline, "Timeline")
+                            .on_hover_text("Animate the PiP over the recording")
+                            .clicked()
+                        {
+                            self.show_timeline = !self.show_timeline;
+                        }
+
+                        let mut key = self.pip_chroma_key.lock().unwrap().clone();
+                        egui::CollapsingHeader::new("Chroma key")
+                            .id_salt("pip_chroma_key")
+                            .show(ui, |ui| {
+                                if chroma_key::controls(ui, &mut key) {
+                                    if let Some(pipeline) = &self.pip_pipeline {
+                                        key.apply(pipeline);
+                                    }
+                                    *self.pip_chroma_key.lock().unwrap() = key;
+                                }
+                            });
+
+                        let mut style = self.pip_style.lock().unwrap().clone();
+                        egui::ComboBox::from_label("Shape")
+                            .selected_text(style.shape.label())
+                            .show_ui(ui, |ui| {
+                                for shape in PipShape::ALL {
+                                    ui.selectable_value(&mut style.shape, shape, shape.label());
+                                }
+                            });
+                        ui.add_enabled(
+                            style.shape.is_square(),
+                            egui::Checkbox::new(&mut style.crop_to_fill, "Crop to fill"),
+                        );
+                        ui.add_enabled(
+                            style.shape != PipShape::Circle,
+                            egui::Slider::new(&mut style.corner_radius, 0.0..=64.0)
+                                .text("Corner radius"),
+                        );
+                        ui.horizontal(|ui| {
+                            ui.add(
+                                egui::Slider::new(&mut style.border_width, 0.0..=16.0)
+                                    .text("Border"),
+                            );
+                            let [r, g, b, a] = style.border_color;
+                            let mut color = egui::Color32::from_rgba_unmultiplied(r, g, b, a);
+                            if ui.color_edit_button_srgba(&mut color).changed() {
+                                style.border_color = color.to_srgba_unmultiplied();
+                            }
+                        });
+                        ui.checkbox(&mut style.shadow, "Drop shadow");
+                        if style.shadow {
+                            ui.add(
+                                egui::Slider::new(&mut style.shadow_blur, 0.0..=32.0).text("Blur"),
+                            );
+                            ui.add(
+                                egui::Slider::new(&mut style.shadow_offset[1], -16.0..=16.0)
+                                    .text("Offset"),
+                            );
+                        }
+
+                        *self.pip_style.lock().unwrap() = style;
+                    }
                 });
         }
 
         // Show PiP window (outside of settings panel)
         if self.show_pip {
-            if let Some(texture) = &self.pip_texture {
-                let mut new_position = self.pip_position;
-                let mut should_update_size = false;
+            self.layout_pip();
+            let ui_scale = self
+                .preview_rect
+                .map(|rect| rect.width() / self.frame_size().x)
+                .unwrap_or(1.0);
+            if let (Some(texture), Some(canvas)) = (&self.pip_texture, self.pip_canvas_video_rect())
+            {
+                let mut drag_pos = self.pip_drag_pos;
+                let mut resize_by = None;
+                let uv = preview_uv(self.mirrors_preview_only(true));
+                let inset = self.pip_inset() * ui_scale;
 
                 egui::Window::new("Webcam")
                     .title_bar(false)
-                    .resizable(true)
-                    .default_size(self.pip_desired_size)
-                    .min_size(egui::vec2(160.0, 120.0))
+                    .resizable(false)
                     .fixed_pos(self.pip_position)
-                    .frame(
-                        egui::Frame::none()
-                            .inner_margin(8.0)
-                            .rounding(8.0)
-                            .fill(egui::Color32::from_rgba_premultiplied(20, 20, 30, 200)),
-                    )
+                    // The styled frame carries its own shape and shadow
+                    .frame(egui::Frame::none())
                     .show(ctx, |ui| {
-                        let available_size = ui.available_size();
-                        let dims = self.pip_dimensions.lock().unwrap();
-                        let aspect_ratio = dims.width as f32 / dims.height as f32;
-
-                        // Calculate display size maintaining aspect ratio
-                        let mut display_size = available_size;
-                        let current_ratio = display_size.x / display_size.y;
-
-                        if current_ratio > aspect_ratio {
-                            display_size.x = display_size.y * aspect_ratio;
-                        } else {
-                            display_size.y = display_size.x / aspect_ratio;
-                        }
-
-                        // Ensure minimum size
-                        display_size.x = display_size.x.max(160.0);
-                        display_size.y = display_size.y.max(120.0);
-
+                        // Same rect the compositor uses, scaled to the preview. The
+                        // recording turns the picture inside it and cuts off the
+                        // corners, so the preview clips them too
+                        let size = canvas.size() * ui_scale;
+                        let clip = egui::Rect::from_min_size(ui.next_widget_position(), size);
+                        ui.set_clip_rect(clip.intersect(ui.clip_rect()));
                         let response = ui.add(
                             egui::Image::new(texture)
-                                .fit_to_exact_size(display_size)
-                                .sense(egui::Sense::drag())
-                                .rounding(4.0),
+                                .uv(uv)
+                                .fit_to_exact_size(size)
+                                .rotate(self.pip_rotation.to_radians(), egui::Vec2::splat(0.5))
+                                .tint(egui::Color32::from_white_alpha(
+                                    (self.pip_opacity * 255.0).round() as u8,
+                                ))
+                                .sense(egui::Sense::drag()),
                         );
 
+                        // Track the raw pointer so a snapped PiP can be pulled free again
+                        if response.drag_started() {
+                            drag_pos = Some(self.pip_position);
+                        }
                         if response.dragged() {
-                            new_position += response.drag_delta();
+                            drag_pos = drag_pos.map(|pos| pos + response.drag_delta());
                         }
-
-                        // Store actual display size
-                        self.pip_size = display_size;
-
-                        // Check if we should update pipeline size
                         if response.drag_stopped() {
-                            should_update_size = true;
+                            drag_pos = None;
+                        }
+
+                        // Resize handle at the shape's bottom-right corner
+                        let corner = response.rect.max - egui::Vec2::splat(inset);
+                        let handle_rect = egui::Rect::from_min_max(
+                            corner - egui::Vec2::splat(PIP_RESIZE_HANDLE),
+                            corner,
+                        );
+                        let handle = ui.interact(
+                            handle_rect,
+                            ui.id().with("pip_resize"),
+                            egui::Sense::drag(),
+                        );
+                        if handle.hovered() || handle.dragged() {
+                            ctx.set_cursor_icon(egui::CursorIcon::ResizeNwSe);
+                            let stroke = egui::Stroke::new(1.5, egui::Color32::WHITE);
+                            for offset in [4.0, 8.0, 12.0] {
+                                ui.painter().line_segment(
+                                    [
+                                        corner - egui::vec2(offset, 2.0),
+                                        corner - egui::vec2(2.0, offset),
+                                    ],
+                                    stroke,
+                                );
+                            }
+                        }
+                        if handle.dragged() {
+                            resize_by = Some(handle.drag_delta() / ui_scale);
                         }
                     });
 
-                // Apply position update
-                self.pip_position = new_position;
+                // Split layouts place the PiP themselves
+                if self.settings.split_layout.is_split() {
+                    drag_pos = None;
+                    resize_by = None;
+                }
 
-                // Update pipeline size if needed
-                if should_update_size {
+                // Apply position update
+                if let Some(pos) = drag_pos {
+                    self.drag_pip_to(pos);
+                }
+                self.pip_drag_pos = drag_pos;
+
+                // Resize live while the handle is dragged, keeping the aspect ratio
+                if let Some(delta) = resize_by {
+                    let aspect = self.pip_aspect_ratio();
+                    let width = (self.pip_video_size.x + delta.x.max(delta.y * aspect))
+                        .clamp(PIP_MIN_WIDTH, self.frame_size().x.max(PIP_MIN_WIDTH));
+                    self.pip_video_size = egui::vec2(width, width / aspect);
                     self.update_pip_size();
                 }
             }
         }
 
+        // Keyframes of the PiP animation
+        if self.show_pip && self.show_timeline {
+            let live = self
+                .recording_state
+                .is_active()
+                .then(|| self.recording_timer.elapsed().as_secs_f32());
+            let current = self.current_pip_keyframe();
+            let mut open = true;
+            let mut seek = false;
+            egui::Window::new("PiP Timeline")
+                .open(&mut open)
+                .default_width(520.0)
+                .anchor(egui::Align2::CENTER_BOTTOM, egui::vec2(0.0, -16.0))
+                .show(ctx, |ui| {
+                    seek =
+                        self.timeline_view
+                            .show(ui, &mut self.settings.pip_timeline, current, live);
+                });
+            self.show_timeline = open;
+            if seek {
+                self.apply_pip_timeline(self.timeline_view.playhead);
+            }
+        }
+
+        // The previous scene fading out over the preview
+        // Split frames carry the background, only a full frame main video mirrors
+        let mirror = self.mirrors_preview_only(false) && !self.settings.split_layout.is_split();
+        if let Some(fade) = &mut self.crossfade {
+            if fade.texture.is_none() {
+                if let Some(frame) = self.crossfade_frame.lock().unwrap().as_ref() {
+                    fade.texture = Some(ctx.load_texture(
+                        "scene-crossfade",
+                        egui::ColorImage::from_rgba_unmultiplied(
+                            [frame.width() as usize, frame.height() as usize],
+                            frame.as_raw(),
+                        ),
+                        egui::TextureOptions::default(),
+                    ));
+                }
+            }
+            if let (Some(texture), Some(rect)) = (&fade.texture, self.preview_rect) {
+                ctx.layer_painter(egui::LayerId::new(
+                    egui::Order::Foreground,
+                    egui::Id::new("scene_crossfade"),
+                ))
+                .image(
+                    texture.id(),
+                    rect,
+                    preview_uv(mirror),
+                    egui::Color32::from_white_alpha((fade.alpha() * 255.0).round() as u8),
+                );
+            }
+        }
+
+        // Markup window for the last screenshot
+        if let Some(view) = &mut self.annotate_view {
+            if !view.show(ctx) {
+                self.annotate_view = None;
+            }
+        }
+
+        // Large countdown number over the preview
+        if let Some(remaining) = self.countdown_remaining().filter(|r| *r > 0) {
+            let painter = ctx.layer_painter(egui::LayerId::new(
+                egui::Order::Foreground,
+                egui::Id::new("countdown_overlay"),
+            ));
+            let center = ctx.screen_rect().center();
+            painter.circle_filled(
+                center,
+                90.0,
+                egui::Color32::from_rgba_premultiplied(10, 10, 15, 200),
+            );
+            painter.text(
+                center,
+                egui::Align2::CENTER_CENTER,
+                remaining.to_string(),
+                FontId::proportional(120.0),
+                egui::Color32::WHITE,
+            );
+        }
+
         // Request continuous repaints for smooth video
         ctx.request_repaint();
     }
 }
 
+/// Feed an appsrc of `pipeline` from `next_frame`, which returns the latest RGBA
+/// frame and its size. Caps follow the frame size so it can change mid-recording.
+fn attach_frame_source(
+    pipeline: &gst::Pipeline,
+    name: &str,
+    next_frame: impl Fn() -> Option<(Vec<u8>, i32, i32)> + Send + Sync + 'static,
+) -> Result<(), anyhow::Error> {
+    let src = pipeline
+        .by_name(name)
+        .ok_or_else(|| anyhow::anyhow!("Failed to find {}", name))?
+        .downcast::<gstreamer_app::AppSrc>()
+        .map_err(|_| anyhow::anyhow!("Failed to downcast to AppSrc"))?;
+
+    src.set_format(gst::Format::Time);
+    src.set_max_bytes(1);
+    src.set_do_timestamp(true);
+
+    let caps_size: Mutex<Option<(i32, i32)>> = Mutex::new(None);
+    src.set_callbacks(
+        gstreamer_app::AppSrcCallbacks::builder()
+            .need_data(move |src, _| {
+                let Some((data, width, height)) = next_frame() else {
+                    return;
+                };
+
+                let mut size = caps_size.lock().unwrap();
+                if *size != Some((width, height)) {
+                    let caps = gst::Caps::builder("video/x-raw")
+                        .field("format", "RGBA")
+                        .field("width", width)
+                        .field("height", height)
+                        .field("framerate", gst::Fraction::new(30, 1))
+                        .build();
+                    src.set_caps(Some(&caps));
+                    *size = Some((width, height));
+                }
+
+                let _ = src.push_buffer(gst::Buffer::from_mut_slice(data));
+            })
+            .build(),
+    );
+
+    Ok(())
+}
+
+/// Fully transparent 2x2 RGBA frame, for appsrcs with nothing to show yet.
+fn blank_frame() -> (Vec<u8>, i32, i32) {
+    (vec![0; 2 * 2 * 4], 2, 2)
+}
+
+/// Texture coordinates for drawing a preview, swapped horizontally to mirror it.
+fn preview_uv(mirror: bool) -> egui::Rect {
+    if mirror {
+        egui::Rect::from_min_max(egui::pos2(1.0, 0.0), egui::pos2(0.0, 1.0))
+    } else {
+        egui::Rect::from_min_max(egui::Pos2::ZERO, egui::pos2(1.0, 1.0))
+    }
+}
+
+fn recording_timestamp() -> String {
+    chrono::Local::now().format("%Y%m%d_%H%M%S").to_string()
+}
+
+/// Down to the millisecond, so screenshots taken within a second don't overwrite each other.
+fn screenshot_timestamp() -> String {
+    chrono::Local::now().format("%Y%m%d_%H%M%S_%3f").to_string()
+}
+
+/// Every file the app writes is named `recording_<timestamp>[_<suffix>].<extension>`.
+fn output_filename(timestamp: &str, suffix: Option<&str>, extension: &str) -> String {
+    match suffix {
+        Some(suffix) => format!("recording_{}_{}.{}", timestamp, suffix, extension),
+        None => format!("recording_{}.{}", timestamp, extension),
+    }
+}
+
 struct ImageDimensions {
     width: i32,
     height: i32,
 }
 
 struct GstreamerSetup {
-    frame_data: Arc<Mutex<Option<Vec<u8>>>>,
-    image_dims: Arc<Mutex<ImageDimensions>>,
     pipeline: gst::Pipeline,
     devices: Vec<MediaDeviceInfo>,
     tx: mpsc::Sender<bool>,
 }
 
-fn setup_gstreamer(device_idx: usize) -> Result<GstreamerSetup, anyhow::Error> {
+fn setup_gstreamer(
+    device_idx: usize,
+    layers: Arc<Mutex<FrameLayers>>,
+    frame_data: Arc<Mutex<Option<Vec<u8>>>>,
+    image_dims: Arc<Mutex<ImageDimensions>>,
+) -> Result<GstreamerSetup, anyhow::Error> {
     let displays = CGDisplay::active_displays().expect("Failed to get active displays");
     println!("Found {} displays", displays.len());
 
-    // Create devices list starting with FaceTime camera
-    let mut devices = vec![MediaDeviceInfo {
-        pipeline_id: 0,
-        kind: MediaDeviceKind::VideoInput,
-        label: "FaceTime Camera".to_string(),
-        setup_pipeline: CAMERA_PIPELINE.to_string(),
-        device_id: None,
-    }];
+    // Create devices list starting with the cameras
+    let mut devices = get_camera_devices();
 
     // Add displays
     for (i, display_id) in displays.iter().enumerate() {
         let bounds = unsafe { CGDisplayBounds(*display_id) };
         devices.push(MediaDeviceInfo {
-            pipeline_id: (i + 1) as u32,
-            kind: MediaDeviceKind::VideoInput,
+            pipeline_id: devices.len() as u32,
+            kind: MediaDeviceKind::Display(*display_id),
             label: format!(
                 "Display {} ({}x{})",
                 i + 1,
@@ -1084,14 +3662,18 @@ fn setup_gstreamer(device_idx: usize) -> Result<GstreamerSetup, anyhow::Error> {
 
     let selected_device = &devices[device_idx];
     println!("Selected device: {:?}", selected_device);
-    println!("Using pipeline: {}", selected_device.setup_pipeline);
+    // Only the overlays whose plugins are installed, so the preview works without them
+    let launch = selected_device.setup_pipeline.replace(
+        "{overlays}",
+        &format!("{}{}", overlays::chain(), keystrokes::chain()),
+    );
+    println!("Using pipeline: {}", launch);
 
-    let pipeline = gst::parse::launch(&selected_device.setup_pipeline)
+    let pipeline = gst::parse::launch(&launch)
         .map_err(|e| anyhow::anyhow!("Failed to create pipeline: {:?}", e))?
         .downcast::<gst::Pipeline>()
         .map_err(|_| anyhow::anyhow!("Failed to downcast to Pipeline"))?;
 
-    let frame_data = Arc::new(Mutex::new(None));
     let frame_data_clone = frame_data.clone();
 
     let appsink = pipeline
@@ -1105,13 +3687,7 @@ fn setup_gstreamer(device_idx: usize) -> Result<GstreamerSetup, anyhow::Error> {
     appsink.set_drop(true);
     appsink.set_sync(false);
 
-    let image_dims = ImageDimensions {
-        width: 0,
-        height: 0,
-    };
-
-    let image_dims_clone = Arc::new(Mutex::new(image_dims));
-    let image_dims_for_callback = image_dims_clone.clone();
+    let image_dims_for_callback = image_dims.clone();
 
     // channel to ask for updated dimensions
     let (tx, rx) = mpsc::channel::<bool>();
@@ -1127,7 +3703,13 @@ fn setup_gstreamer(device_idx: usize) -> Result<GstreamerSetup, anyhow::Error> {
                     gst::FlowError::Error
                 })?;
 
-                if rx.try_recv().is_ok() {
+                // Re-read the caps when asked to, or when the frame size changed under us
+                let frame_size = sample.buffer().map(|buffer| buffer.size()).unwrap_or(0);
+                let size_changed = {
+                    let dims = image_dims_for_callback.lock().unwrap();
+                    frame_size != (dims.width * dims.height * 4) as usize
+                };
+                if rx.try_recv().is_ok() || size_changed {
                     let caps = sample.caps().ok_or(gst::FlowError::Error)?;
                     println!("Caps: {:?}", caps);
                     let mut dims = image_dims_for_callback.lock().unwrap();
@@ -1151,8 +3733,17 @@ fn setup_gstreamer(device_idx: usize) -> Result<GstreamerSetup, anyhow::Error> {
                 let buffer = sample.buffer().ok_or(gst::FlowError::Error)?;
                 let map = buffer.map_readable().map_err(|_| gst::FlowError::Error)?;
 
+                // Masks and cursor effects go into the frame itself, so preview,
+                // recording and screenshots all show the same thing
+                let mut frame = map.as_ref().to_vec();
+                let (width, height) = {
+                    let dims = image_dims_for_callback.lock().unwrap();
+                    (dims.width as usize, dims.height as usize)
+                };
+                layers.lock().unwrap().draw(&mut frame, width, height);
+
                 let mut data = frame_data_clone.lock().unwrap();
-                *data = Some(map.as_ref().to_vec());
+                *data = Some(frame);
 
                 Ok(gst::FlowSuccess::Ok)
             })
@@ -1180,7 +3771,7 @@ fn setup_gstreamer(device_idx: usize) -> Result<GstreamerSetup, anyhow::Error> {
 
     // Get the current dimensions and update if needed
     {
-        let mut dims = image_dims_clone.lock().unwrap();
+        let mut dims = image_dims.lock().unwrap();
         if dims.width == 0 || dims.height == 0 {
             dims.width = 1280;
             dims.height = 720;
@@ -1192,14 +3783,49 @@ fn setup_gstreamer(device_idx: usize) -> Result<GstreamerSetup, anyhow::Error> {
     } // Lock is released here
 
     Ok(GstreamerSetup {
-        frame_data,
-        image_dims: image_dims_clone,
         pipeline,
         devices,
         tx,
     })
 }
 
+fn get_camera_devices() -> Vec<MediaDeviceInfo> {
+    let mut devices = Vec::new();
+    let monitor = DeviceMonitor::new();
+
+    monitor.set_show_all_devices(true);
+    let _ = monitor.start();
+
+    // avfvideosrc numbers cameras in the order the monitor reports them
+    for device in monitor.devices() {
+        if device.device_class().contains("Video/Source") {
+            devices.push(MediaDeviceInfo {
+                pipeline_id: devices.len() as u32,
+                kind: MediaDeviceKind::VideoInput,
+                label: device.display_name().to_string(),
+                setup_pipeline: CAMERA_PIPELINE.replace("{}", &devices.len().to_string()),
+                device_id: None,
+            });
+        }
+    }
+
+    monitor.stop();
+
+    // If no cameras were found, fall back to the built-in one
+    if devices.is_empty() {
+        devices.push(MediaDeviceInfo {
+            pipeline_id: 0,
+            kind: MediaDeviceKind::VideoInput,
+            label: "FaceTime Camera".to_string(),
+            setup_pipeline: CAMERA_PIPELINE.replace("{}", "0"),
+            device_id: None,
+        });
+    }
+
+    println!("Found {} cameras: {:?}", devices.len(), devices);
+    devices
+}
+
 fn get_audio_devices() -> Vec<MediaDeviceInfo> {
     let mut devices = Vec::new();
     let monitor = DeviceMonitor::new();
@@ -1223,11 +3849,17 @@ fn get_audio_devices() -> Vec<MediaDeviceInfo> {
                 None
             };
 
+            let setup_pipeline = device_id
+                .as_ref()
+                .and_then(|id| id.parse::<u32>().ok())
+                .map(|id| format!("osxaudiosrc device={}", id))
+                .unwrap_or_else(|| "osxaudiosrc".to_string());
+
             devices.push(MediaDeviceInfo {
                 pipeline_id: devices.len() as u32,
                 kind: MediaDeviceKind::AudioInput,
                 label: device.display_name().to_string(),
-                setup_pipeline: String::new(),
+                setup_pipeline,
                 device_id,
             });
         }
@@ -1241,7 +3873,7 @@ fn get_audio_devices() -> Vec<MediaDeviceInfo> {
             pipeline_id: 0,
             kind: MediaDeviceKind::AudioInput,
             label: "Default Microphone".to_string(),
-            setup_pipeline: String::new(),
+            setup_pipeline: "osxaudiosrc".to_string(),
             device_id: None,
         });
     }
@@ -0,0 +1,117 @@
+use gstreamer as gst;
+use gstreamer::prelude::*;
+
+/// Microphone processing applied before metering and encoding. Everything here
+/// can be changed while a pipeline is playing, see `apply`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MicSettings {
+    /// Linear gain, 1.0 is unity.
+    pub gain: f64,
+    /// Keeps the audio track but replaces its content with silence.
+    pub muted: bool,
+    pub gate_enabled: bool,
+    pub gate_threshold_db: f64,
+    /// Whether the denoiser is inserted when a pipeline is built.
+    pub noise_suppression: bool,
+    /// 0.0 - 1.0, mapped onto the denoiser's own strength property.
+    pub suppression_strength: f64,
+}
+
+impl Default for MicSettings {
+    fn default() -> Self {
+        Self {
+            gain: 1.0,
+            muted: false,
+            gate_enabled: false,
+            gate_threshold_db: -45.0,
+            noise_suppression: false,
+            suppression_strength: 0.5,
+        }
+    }
+}
+
+/// Noise suppression plugins we know how to drive, neither ships with core GStreamer.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Denoiser {
+    Rnnoise,
+    WebRtc,
+}
+
+impl Denoiser {
+    pub fn detect() -> Option<Self> {
+        if gst::ElementFactory::find("audiornnoise").is_some() {
+            Some(Denoiser::Rnnoise)
+        } else if gst::ElementFactory::find("webrtcdsp").is_some() {
+            Some(Denoiser::WebRtc)
+        } else {
+            None
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Denoiser::Rnnoise => "RNNoise",
+            Denoiser::WebRtc => "WebRTC",
+        }
+    }
+}
+
+/// Pipeline fragment for gain, mute, noise suppression and the noise gate.
+/// Expects raw audio on its input and produces raw audio on its output.
+pub fn processing_chain(settings: &MicSettings, denoiser: Option<Denoiser>) -> String {
+    let mut chain = format!(
+        "volume name=mic_volume volume={} mute={} ! ",
+        settings.gain, settings.muted
+    );
+
+    if settings.noise_suppression {
+        match denoiser {
+            Some(Denoiser::Rnnoise) => chain.push_str(
+                "audioconvert ! audioresample ! audio/x-raw,format=F32LE,rate=48000 ! \
+                 audiornnoise name=mic_denoise ! audioconvert ! audioresample ! ",
+            ),
+            Some(Denoiser::WebRtc) => chain.push_str(
+                "audioconvert ! audioresample ! audio/x-raw,rate=48000 ! \
+                 webrtcdsp name=mic_denoise echo-cancel=false gain-control=false ! \
+                 audioconvert ! audioresample ! ",
+            ),
+            None => {}
+        }
+    }
+
+    chain.push_str(
+        "audioconvert ! audiodynamic name=mic_gate mode=expander characteristics=hard-knee",
+    );
+    chain
+}
+
+/// Push the current settings onto a playing pipeline built with `processing_chain`.
+pub fn apply(settings: &MicSettings, pipeline: &gst::Pipeline) {
+    if let Some(volume) = pipeline.by_name("mic_volume") {
+        volume.set_property("volume", settings.gain);
+        volume.set_property("mute", settings.muted);
+    }
+
+    if let Some(gate) = pipeline.by_name("mic_gate") {
+        // A downward expander with a steep ratio acts as a gate, ratio 1 is a no-op
+        let threshold = 10f64.powf(settings.gate_threshold_db / 20.0) as f32;
+        let ratio: f32 = if settings.gate_enabled { 10.0 } else { 1.0 };
+        gate.set_property("threshold", threshold);
+        gate.set_property("ratio", ratio);
+    }
+
+    if let Some(denoise) = pipeline.by_name("mic_denoise") {
+        let strength = settings.suppression_strength.clamp(0.0, 1.0);
+        if denoise.has_property("voice-activity-threshold", None) {
+            denoise.set_property("voice-activity-threshold", strength as f32);
+        } else if denoise.has_property("noise-suppression-level", None) {
+            let level = match (strength * 3.0).round() as u32 {
+                0 => "low",
+                1 => "moderate",
+                2 => "high",
+                _ => "very-high",
+            };
+            denoise.set_property_from_str("noise-suppression-level", level);
+        }
+    }
+}
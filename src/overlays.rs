@@ -0,0 +1,351 @@
+use eframe::egui;
+use gstreamer as gst;
+use gstreamer::prelude::*;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum OverlayPosition {
+    TopLeft,
+    TopCenter,
+    TopRight,
+    BottomLeft,
+    BottomCenter,
+    BottomRight,
+}
+
+impl OverlayPosition {
+    pub const ALL: [OverlayPosition; 6] = [
+        OverlayPosition::TopLeft,
+        OverlayPosition::TopCenter,
+        OverlayPosition::TopRight,
+        OverlayPosition::BottomLeft,
+        OverlayPosition::BottomCenter,
+        OverlayPosition::BottomRight,
+    ];
+
+    /// Images are placed by edge offsets, which can't center them.
+    pub const CORNERS: [OverlayPosition; 4] = [
+        OverlayPosition::TopLeft,
+        OverlayPosition::TopRight,
+        OverlayPosition::BottomLeft,
+        OverlayPosition::BottomRight,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            OverlayPosition::TopLeft => "Top left",
+            OverlayPosition::TopCenter => "Top center",
+            OverlayPosition::TopRight => "Top right",
+            OverlayPosition::BottomLeft => "Bottom left",
+            OverlayPosition::BottomCenter => "Bottom center",
+            OverlayPosition::BottomRight => "Bottom right",
+        }
+    }
+
+    fn halignment(&self) -> &'static str {
+        match self {
+            OverlayPosition::TopLeft | OverlayPosition::BottomLeft => "left",
+            OverlayPosition::TopCenter | OverlayPosition::BottomCenter => "center",
+            OverlayPosition::TopRight | OverlayPosition::BottomRight => "right",
+        }
+    }
+
+    fn valignment(&self) -> &'static str {
+        match self {
+            OverlayPosition::TopLeft | OverlayPosition::TopCenter | OverlayPosition::TopRight => {
+                "top"
+            }
+            _ => "bottom",
+        }
+    }
+
+    /// Offsets from the edges, negative values count from the right or bottom.
+    fn edge_offsets(&self, margin: i32) -> (i32, i32) {
+        let right = matches!(
+            self,
+            OverlayPosition::TopRight | OverlayPosition::BottomRight
+        );
+        let bottom = matches!(
+            self,
+            OverlayPosition::BottomLeft
+                | OverlayPosition::BottomCenter
+                | OverlayPosition::BottomRight
+        );
+        (
+            if right { -margin } else { margin },
+            if bottom { -margin } else { margin },
+        )
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TextLayer {
+    pub enabled: bool,
+    /// The text itself, or the strftime format for the timestamp layer.
+    pub text: String,
+    pub position: OverlayPosition,
+    pub font_family: String,
+    pub font_size: u32,
+    pub color: [u8; 3],
+    pub opacity: f32,
+    pub background: bool,
+}
+
+impl Default for TextLayer {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            text: String::new(),
+            position: OverlayPosition::TopLeft,
+            font_family: "Sans".to_string(),
+            font_size: 24,
+            color: [255, 255, 255],
+            opacity: 1.0,
+            background: false,
+        }
+    }
+}
+
+impl TextLayer {
+    /// Push the layer into a `textoverlay` or `clockoverlay` element.
+    fn apply(&self, element: &gst::Element) {
+        element.set_property("silent", !self.enabled);
+        element.set_property(
+            "font-desc",
+            format!("{} {}", self.font_family, self.font_size),
+        );
+        element.set_property_from_str("halignment", self.position.halignment());
+        element.set_property_from_str("valignment", self.position.valignment());
+        element.set_property("shaded-background", self.background);
+
+        // ARGB, with the opacity as alpha
+        let [r, g, b] = self.color;
+        let alpha = (self.opacity.clamp(0.0, 1.0) * 255.0).round() as u32;
+        element.set_property(
+            "color",
+            (alpha << 24) | ((r as u32) << 16) | ((g as u32) << 8) | b as u32,
+        );
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LogoLayer {
+    pub enabled: bool,
+    pub path: String,
+    pub position: OverlayPosition,
+    /// Width in video pixels, the height keeps the image's aspect ratio.
+    pub width: i32,
+    pub opacity: f64,
+}
+
+impl Default for LogoLayer {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            path: String::new(),
+            position: OverlayPosition::BottomRight,
+            width: 160,
+            opacity: 0.8,
+        }
+    }
+}
+
+/// Overlay elements for the source pipelines, each followed by ` ! `. The
+/// pango and gdkpixbuf plugins are optional, overlays they provide are left
+/// out when they are missing.
+pub fn chain() -> String {
+    let mut chain = String::new();
+    if gst::ElementFactory::find("textoverlay").is_some()
+        && gst::ElementFactory::find("clockoverlay").is_some()
+    {
+        chain.push_str(
+            "textoverlay name=title silent=true ! clockoverlay name=clock silent=true ! ",
+        );
+    } else {
+        eprintln!("Pango plugin not found, title and timestamp overlays are unavailable");
+    }
+    if gst::ElementFactory::find("gdkpixbufoverlay").is_some() {
+        chain.push_str("gdkpixbufoverlay name=logo ! ");
+    } else {
+        eprintln!("gdkpixbuf plugin not found, logo overlay is unavailable");
+    }
+    chain
+}
+
+/// Burned-in layers on the main video. Source pipelines draw them with
+/// `textoverlay name=title`, `clockoverlay name=clock` and
+/// `gdkpixbufoverlay name=logo`, so they show in the preview and recordings.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Overlays {
+    pub title: TextLayer,
+    pub timestamp: TextLayer,
+    pub logo: LogoLayer,
+    /// Distance from the frame edges, in video pixels.
+    pub margin: i32,
+}
+
+impl Default for Overlays {
+    fn default() -> Self {
+        Self {
+            title: TextLayer {
+                text: "Title".to_string(),
+                ..TextLayer::default()
+            },
+            timestamp: TextLayer {
+                text: "%Y-%m-%d %H:%M:%S".to_string(),
+                position: OverlayPosition::TopRight,
+                font_family: "Monospace".to_string(),
+                font_size: 18,
+                background: true,
+                ..TextLayer::default()
+            },
+            logo: LogoLayer::default(),
+            margin: 24,
+        }
+    }
+}
+
+impl Overlays {
+    pub fn apply(&self, pipeline: &gst::Pipeline) {
+        let margin = self.margin;
+
+        if let Some(title) = pipeline.by_name("title") {
+            self.title.apply(&title);
+            title.set_property("text", &self.title.text);
+            title.set_property("xpad", margin);
+            title.set_property("ypad", margin);
+        }
+
+        if let Some(clock) = pipeline.by_name("clock") {
+            self.timestamp.apply(&clock);
+            clock.set_property("time-format", &self.timestamp.text);
+            clock.set_property("xpad", margin);
+            clock.set_property("ypad", margin);
+        }
+
+        if let Some(logo) = pipeline.by_name("logo") {
+            // Only load images that exist, a bad path errors the whole pipeline
+            let visible = self.logo.enabled && std::path::Path::new(&self.logo.path).is_file();
+            if visible {
+                let current = logo.property::<Option<String>>("location");
+                if current.as_deref() != Some(self.logo.path.as_str()) {
+                    logo.set_property("location", &self.logo.path);
+                }
+                let (x, y) = self.logo.position.edge_offsets(margin);
+                logo.set_property("offset-x", x);
+                logo.set_property("offset-y", y);
+                logo.set_property("overlay-width", self.logo.width);
+            }
+            logo.set_property("alpha", if visible { self.logo.opacity } else { 0.0 });
+        }
+    }
+}
+
+fn text_layer_controls(
+    ui: &mut egui::Ui,
+    id: &str,
+    text_label: &str,
+    layer: &mut TextLayer,
+) -> bool {
+    let mut changed = false;
+    ui.horizontal(|ui| {
+        ui.label(text_label);
+        changed |= ui.text_edit_singleline(&mut layer.text).changed();
+    });
+    ui.horizontal(|ui| {
+        egui::ComboBox::from_id_salt((id, "position"))
+            .selected_text(layer.position.label())
+            .show_ui(ui, |ui| {
+                for position in OverlayPosition::ALL {
+                    changed |= ui
+                        .selectable_value(&mut layer.position, position, position.label())
+                        .changed();
+                }
+            });
+        changed |= ui.checkbox(&mut layer.background, "Background").changed();
+    });
+    ui.horizontal(|ui| {
+        ui.label("Font");
+        changed |= ui
+            .add(egui::TextEdit::singleline(&mut layer.font_family).desired_width(100.0))
+            .changed();
+        changed |= ui
+            .add(
+                egui::DragValue::new(&mut layer.font_size)
+                    .range(6..=200)
+                    .suffix(" pt"),
+            )
+            .changed();
+        changed |= ui.color_edit_button_srgb(&mut layer.color).changed();
+    });
+    changed |= ui
+        .add(egui::Slider::new(&mut layer.opacity, 0.0..=1.0).text("Opacity"))
+        .changed();
+    changed
+}
+
+/// Controls for all overlay layers, returns true when anything changed.
+pub fn controls(ui: &mut egui::Ui, overlays: &mut Overlays) -> bool {
+    let mut changed = false;
+
+    changed |= ui.checkbox(&mut overlays.title.enabled, "Title").changed();
+    if overlays.title.enabled {
+        changed |= text_layer_controls(ui, "title", "Text", &mut overlays.title);
+    }
+
+    changed |= ui
+        .checkbox(&mut overlays.timestamp.enabled, "Timestamp")
+        .changed();
+    if overlays.timestamp.enabled {
+        changed |= text_layer_controls(ui, "timestamp", "Format", &mut overlays.timestamp);
+    }
+
+    changed |= ui.checkbox(&mut overlays.logo.enabled, "Logo").changed();
+    if overlays.logo.enabled {
+        let logo = &mut overlays.logo;
+        ui.horizontal(|ui| {
+            ui.label("Image");
+            changed |= ui.text_edit_singleline(&mut logo.path).changed();
+        });
+        if !logo.path.is_empty() && !std::path::Path::new(&logo.path).is_file() {
+            ui.label(
+                egui::RichText::new("Image not found")
+                    .size(11.0)
+                    .color(egui::Color32::from_rgb(255, 120, 120)),
+            );
+        }
+        ui.horizontal(|ui| {
+            egui::ComboBox::from_id_salt("logo_position")
+                .selected_text(logo.position.label())
+                .show_ui(ui, |ui| {
+                    for position in OverlayPosition::CORNERS {
+                        changed |= ui
+                            .selectable_value(&mut logo.position, position, position.label())
+                            .changed();
+                    }
+                });
+            changed |= ui
+                .add(
+                    egui::DragValue::new(&mut logo.width)
+                        .range(16..=1920)
+                        .suffix(" px"),
+                )
+                .changed();
+        });
+        changed |= ui
+            .add(egui::Slider::new(&mut logo.opacity, 0.0..=1.0).text("Opacity"))
+            .changed();
+    }
+
+    changed |= ui
+        .add(
+            egui::Slider::new(&mut overlays.margin, 0..=200)
+                .suffix(" px")
+                .text("Margin"),
+        )
+        .changed();
+    changed
+}
@@ -0,0 +1,458 @@
+use crate::pip_style::{PipStyle, StyledFrame};
+use crate::recording_state::RecordingTimer;
+use eframe::egui;
+use gstreamer as gst;
+use gstreamer::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// Keyframes closer together than this, in seconds, are the same keyframe.
+const SAME_TIME: f32 = 0.05;
+/// How often the animator samples the timeline, about once a frame.
+const STEP_INTERVAL: Duration = Duration::from_millis(16);
+/// Shortest timeline shown, in seconds.
+const MIN_LENGTH: f32 = 30.0;
+const STRIP_HEIGHT: f32 = 36.0;
+
+/// PiP placement and look at one point of the recording.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PipKeyframe {
+    /// Seconds from the start of the recording, not counting pauses.
+    pub time: f32,
+    /// Top-left of the PiP canvas as a fraction of the frame.
+    pub offset: [f32; 2],
+    /// Width of the visible shape in video pixels.
+    pub width: f32,
+    /// Degrees clockwise.
+    pub rotation: f32,
+    pub opacity: f32,
+    pub border_width: f32,
+}
+
+impl PipKeyframe {
+    fn lerp(&self, other: &PipKeyframe, t: f32) -> PipKeyframe {
+        let mix = |a: f32, b: f32| a + (b - a) * t;
+        PipKeyframe {
+            time: mix(self.time, other.time),
+            offset: [
+                mix(self.offset[0], other.offset[0]),
+                mix(self.offset[1], other.offset[1]),
+            ],
+            width: mix(self.width, other.width),
+            rotation: mix(self.rotation, other.rotation),
+            opacity: mix(self.opacity, other.opacity),
+            border_width: mix(self.border_width, other.border_width),
+        }
+    }
+}
+
+/// PiP animation over the recording timeline. Played into the compositor's
+/// PiP pad by the [`PipAnimator`] while recording.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PipTimeline {
+    pub enabled: bool,
+    /// Ease in and out of each keyframe instead of moving at a constant speed.
+    pub smooth: bool,
+    /// Sorted by time.
+    pub keyframes: Vec<PipKeyframe>,
+}
+
+impl PipTimeline {
+    /// The PiP state at `time`, holding the first and last keyframes before and after them.
+    pub fn sample(&self, time: f32) -> Option<PipKeyframe> {
+        if !self.enabled {
+            return None;
+        }
+        let next = self.keyframes.iter().position(|k| k.time > time);
+        match next {
+            Some(0) => self.keyframes.first().cloned(),
+            Some(idx) => {
+                let (a, b) = (&self.keyframes[idx - 1], &self.keyframes[idx]);
+                let mut t = ((time - a.time) / (b.time - a.time).max(f32::EPSILON)).clamp(0.0, 1.0);
+                if self.smooth {
+                    t = t * t * (3.0 - 2.0 * t);
+                }
+                Some(a.lerp(b, t))
+            }
+            None => self.keyframes.last().cloned(),
+        }
+    }
+
+    /// Whether any keyframe turns the PiP.
+    pub fn rotates(&self) -> bool {
+        self.enabled && self.keyframes.iter().any(|k| k.rotation != 0.0)
+    }
+
+    /// Add `keyframe`, replacing one at the same time. Returns its index.
+    pub fn insert(&mut self, keyframe: PipKeyframe) -> usize {
+        if let Some(idx) = self
+            .keyframes
+            .iter()
+            .position(|k| (k.time - keyframe.time).abs() < SAME_TIME)
+        {
+            self.keyframes[idx] = keyframe;
+            return idx;
+        }
+        let idx = self
+            .keyframes
+            .iter()
+            .position(|k| k.time > keyframe.time)
+            .unwrap_or(self.keyframes.len());
+        self.keyframes.insert(idx, keyframe);
+        idx
+    }
+
+    /// Move keyframe `idx` to `time`, keeping the list sorted. Returns its new index.
+    fn retime(&mut self, idx: usize, time: f32) -> usize {
+        let mut keyframe = self.keyframes.remove(idx);
+        keyframe.time = time.max(0.0);
+        let idx = self
+            .keyframes
+            .iter()
+            .position(|k| k.time > keyframe.time)
+            .unwrap_or(self.keyframes.len());
+        self.keyframes.insert(idx, keyframe);
+        idx
+    }
+
+    fn length(&self) -> f32 {
+        let last = self.keyframes.last().map(|k| k.time).unwrap_or(0.0);
+        (last + 5.0).max(MIN_LENGTH)
+    }
+}
+
+/// The recording whose PiP the timeline moves.
+pub struct AnimationTarget {
+    /// Recording pipeline, its `comp` sink_1 pad and `pip_rotate` get moved.
+    pub recording: gst::Pipeline,
+    /// PiP capture pipeline, its `size` caps follow the animated width.
+    pub capture: gst::Pipeline,
+    /// Size of the recorded frame in pixels.
+    pub frame: egui::Vec2,
+    /// Size of the camera picture.
+    pub camera: (i32, i32),
+}
+
+struct Animated {
+    timeline: PipTimeline,
+    timer: RecordingTimer,
+    target: Option<AnimationTarget>,
+    style: Arc<Mutex<PipStyle>>,
+    styled: Arc<Mutex<Option<StyledFrame>>>,
+    /// Width last asked of the capture, so its caps only change with it.
+    capture_width: i32,
+}
+
+impl Animated {
+    fn step(&mut self) {
+        let Some(target) = &self.target else {
+            return;
+        };
+        let Some(keyframe) = self.timeline.sample(self.timer.elapsed().as_secs_f32()) else {
+            return;
+        };
+
+        let (aspect, capture_width, padding) = {
+            let mut style = self.style.lock().unwrap();
+            style.border_width = keyframe.border_width;
+            let (width, height) = target.camera;
+            (
+                style.shape_aspect(width, height),
+                style.capture_width(keyframe.width, width, height),
+                style.padding(),
+            )
+        };
+        // The frames in flight may still have the old padding
+        let padding = self
+            .styled
+            .lock()
+            .unwrap()
+            .as_ref()
+            .map_or(padding, |styled| styled.padding);
+
+        let size = egui::vec2(keyframe.width, keyframe.width / aspect);
+        let canvas = egui::Rect::from_min_size(
+            (egui::vec2(keyframe.offset[0], keyframe.offset[1]) * target.frame).to_pos2(),
+            size + egui::Vec2::splat(padding as f32 * 2.0),
+        );
+        let pad = target
+            .recording
+            .by_name("comp")
+            .and_then(|comp| comp.static_pad("sink_1"));
+        if let Some(pad) = pad {
+            pad.set_property("xpos", canvas.left().round() as i32);
+            pad.set_property("ypos", canvas.top().round() as i32);
+            pad.set_property("width", canvas.width().round() as i32);
+            pad.set_property("height", canvas.height().round() as i32);
+            pad.set_property("alpha", keyframe.opacity.clamp(0.0, 1.0) as f64);
+        }
+        if let Some(rotate) = target.recording.by_name("pip_rotate") {
+            rotate.set_property("angle", (keyframe.rotation as f64).to_radians());
+        }
+
+        if capture_width != self.capture_width {
+            if let Some(size) = target.capture.by_name("size") {
+                let caps = gst::Caps::builder("video/x-raw")
+                    .field("width", capture_width)
+                    .field("pixel-aspect-ratio", gst::Fraction::new(1, 1))
+                    .build();
+                size.set_property("caps", &caps);
+                self.capture_width = capture_width;
+            }
+        }
+    }
+}
+
+/// Plays the timeline into the recording on a thread of its own, so the PiP
+/// keeps moving while the window is hidden and not repainting. The UI only
+/// follows along on the preview. The thread stops once this is dropped.
+pub struct PipAnimator {
+    animated: Arc<Mutex<Animated>>,
+}
+
+impl PipAnimator {
+    /// Animate with the shared style, whose border follows the timeline, and
+    /// the latest styled frame, whose padding frames the PiP.
+    pub fn spawn(style: Arc<Mutex<PipStyle>>, styled: Arc<Mutex<Option<StyledFrame>>>) -> Self {
+        let animated = Arc::new(Mutex::new(Animated {
+            timeline: PipTimeline::default(),
+            timer: RecordingTimer::default(),
+            target: None,
+            style,
+            styled,
+            capture_width: 0,
+        }));
+        let weak = Arc::downgrade(&animated);
+        std::thread::spawn(move || {
+            while let Some(animated) = weak.upgrade() {
+                animated.lock().unwrap().step();
+                drop(animated);
+                std::thread::sleep(STEP_INTERVAL);
+            }
+        });
+        Self { animated }
+    }
+
+    pub fn set_timeline(&self, timeline: &PipTimeline) {
+        let mut animated = self.animated.lock().unwrap();
+        if animated.timeline != *timeline {
+            animated.timeline = timeline.clone();
+        }
+    }
+
+    /// The recording's clock, the timeline plays along with it.
+    pub fn set_timer(&self, timer: RecordingTimer) {
+        self.animated.lock().unwrap().timer = timer;
+    }
+
+    /// Move the PiP of `target`, or leave it to the UI when None.
+    pub fn set_target(&self, target: Option<AnimationTarget>) {
+        let mut animated = self.animated.lock().unwrap();
+        if target.is_none() {
+            animated.capture_width = 0;
+        }
+        animated.target = target;
+    }
+}
+
+/// Playhead and selection of the timeline panel.
+#[derive(Default)]
+pub struct TimelineView {
+    /// Seconds, only moved by hand while not recording.
+    pub playhead: f32,
+    pub selected: Option<usize>,
+    /// Keyframe being dragged along the strip.
+    dragging: Option<usize>,
+}
+
+impl TimelineView {
+    /// Draw the timeline panel. `current` is the PiP as it is now, stored when
+    /// setting a keyframe, and `live` the recording time while recording.
+    /// Returns true when the playhead moved or a keyframe changed while not
+    /// recording, so the PiP should jump to the timeline's state.
+    pub fn show(
+        &mut self,
+        ui: &mut egui::Ui,
+        timeline: &mut PipTimeline,
+        current: PipKeyframe,
+        live: Option<f32>,
+    ) -> bool {
+        let mut seek = false;
+        if let Some(time) = live {
+            self.playhead = time;
+        }
+
+        ui.horizontal(|ui| {
+            ui.checkbox(&mut timeline.enabled, "Animate");
+            ui.checkbox(&mut timeline.smooth, "Smooth");
+            ui.label(
+                egui::RichText::new(format!("{:.2} s", self.playhead))
+                    .monospace()
+                    .color(if live.is_some() {
+                        egui::Color32::from_rgb(255, 80, 80)
+                    } else {
+                        egui::Color32::LIGHT_GRAY
+                    }),
+            );
+        });
+
+        seek |= self.strip(ui, timeline, live.is_none());
+
+        ui.add_enabled_ui(live.is_none(), |ui| {
+            ui.horizontal(|ui| {
+                if ui
+                    .button("Set keyframe")
+                    .on_hover_text("Store the PiP as it is now at the playhead")
+                    .clicked()
+                {
+                    self.selected = Some(timeline.insert(PipKeyframe {
+                        time: self.playhead,
+                        ..current
+                    }));
+                    timeline.enabled = true;
+                }
+                if ui
+                    .add_enabled(self.selected.is_some(), egui::Button::new("Delete"))
+                    .clicked()
+                {
+                    if let Some(idx) = self.selected.take() {
+                        if idx < timeline.keyframes.len() {
+                            timeline.keyframes.remove(idx);
+                        }
+                    }
+                }
+                if ui
+                    .add_enabled(!timeline.keyframes.is_empty(), egui::Button::new("Clear"))
+                    .clicked()
+                {
+                    timeline.keyframes.clear();
+                    self.selected = None;
+                }
+            });
+
+            // Fine tuning of the selected keyframe
+            let Some(idx) = self.selected.filter(|idx| *idx < timeline.keyframes.len()) else {
+                return;
+            };
+            let mut time = timeline.keyframes[idx].time;
+            ui.horizontal(|ui| {
+                ui.label("Time");
+                if ui
+                    .add(
+                        egui::DragValue::new(&mut time)
+                            .range(0.0..=3600.0)
+                            .speed(0.05)
+                            .suffix(" s"),
+                    )
+                    .changed()
+                {
+                    self.selected = Some(timeline.retime(idx, time));
+                    seek = true;
+                }
+            });
+            let Some(keyframe) = self
+                .selected
+                .and_then(|idx| timeline.keyframes.get_mut(idx))
+            else {
+                return;
+            };
+            seek |= ui
+                .add(
+                    egui::Slider::new(&mut keyframe.rotation, -180.0..=180.0)
+                        .suffix("°")
+                        .text("Rotation"),
+                )
+                .changed();
+            seek |= ui
+                .add(egui::Slider::new(&mut keyframe.opacity, 0.0..=1.0).text("Opacity"))
+                .changed();
+            seek |= ui
+                .add(egui::Slider::new(&mut keyframe.border_width, 0.0..=16.0).text("Border"))
+                .changed();
+        });
+
+        seek && live.is_none()
+    }
+
+    /// The strip with a tick per second, keyframe diamonds and the playhead.
+    /// Clicking seeks, dragging a diamond moves its keyframe.
+    fn strip(&mut self, ui: &mut egui::Ui, timeline: &mut PipTimeline, editable: bool) -> bool {
+        let (rect, response) = ui.allocate_exact_size(
+            egui::vec2(ui.available_width(), STRIP_HEIGHT),
+            if editable {
+                egui::Sense::click_and_drag()
+            } else {
+                egui::Sense::hover()
+            },
+        );
+        let length = timeline.length();
+        let to_x = |time: f32| rect.left() + time / length * rect.width();
+        let to_time = |x: f32| ((x - rect.left()) / rect.width() * length).clamp(0.0, length);
+
+        let mut seek = false;
+        if let Some(pos) = response.interact_pointer_pos() {
+            if response.drag_started() || response.clicked() {
+                // Grab a diamond under the pointer, or just seek
+                self.dragging = timeline
+                    .keyframes
+                    .iter()
+                    .position(|k| (to_x(k.time) - pos.x).abs() < 6.0);
+                self.selected = self.dragging.or(self.selected);
+            }
+            match self.dragging {
+                Some(idx) if response.dragged() => {
+                    let idx = timeline.retime(idx, to_time(pos.x));
+                    self.dragging = Some(idx);
+                    self.selected = Some(idx);
+                    self.playhead = timeline.keyframes[idx].time;
+                }
+                _ => self.playhead = to_time(pos.x),
+            }
+            seek = true;
+        }
+        if response.drag_stopped() {
+            self.dragging = None;
+        }
+
+        let painter = ui.painter_at(rect);
+        painter.rect_filled(rect, 4.0, egui::Color32::from_rgb(30, 30, 40));
+        for second in 0..=length as u32 {
+            let x = to_x(second as f32);
+            let tick = if second % 5 == 0 { 10.0 } else { 5.0 };
+            painter.line_segment(
+                [
+                    egui::pos2(x, rect.bottom() - tick),
+                    egui::pos2(x, rect.bottom()),
+                ],
+                egui::Stroke::new(1.0, egui::Color32::from_gray(90)),
+            );
+        }
+        for (idx, keyframe) in timeline.keyframes.iter().enumerate() {
+            let center = egui::pos2(to_x(keyframe.time), rect.center().y);
+            let color = if self.selected == Some(idx) {
+                egui::Color32::from_rgb(255, 200, 80)
+            } else {
+                egui::Color32::from_rgb(120, 200, 255)
+            };
+            painter.add(egui::Shape::convex_polygon(
+                vec![
+                    center + egui::vec2(0.0, -6.0),
+                    center + egui::vec2(6.0, 0.0),
+                    center + egui::vec2(0.0, 6.0),
+                    center + egui::vec2(-6.0, 0.0),
+                ],
+                color,
+                egui::Stroke::NONE,
+            ));
+        }
+        let x = to_x(self.playhead.min(length));
+        painter.line_segment(
+            [egui::pos2(x, rect.top()), egui::pos2(x, rect.bottom())],
+            egui::Stroke::new(2.0, egui::Color32::from_rgb(255, 80, 80)),
+        );
+
+        seek
+    }
+}
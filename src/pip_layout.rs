@@ -0,0 +1,72 @@
+use eframe::egui;
+use serde::{Deserialize, Serialize};
+
+/// Preset spots for the PiP overlay, measured against the video frame.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum PipAnchor {
+    TopLeft,
+    TopRight,
+    BottomLeft,
+    BottomRight,
+    BottomCenter,
+}
+
+impl PipAnchor {
+    pub const ALL: [PipAnchor; 5] = [
+        PipAnchor::TopLeft,
+        PipAnchor::TopRight,
+        PipAnchor::BottomLeft,
+        PipAnchor::BottomRight,
+        PipAnchor::BottomCenter,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            PipAnchor::TopLeft => "Top left",
+            PipAnchor::TopRight => "Top right",
+            PipAnchor::BottomLeft => "Bottom left",
+            PipAnchor::BottomRight => "Bottom right",
+            PipAnchor::BottomCenter => "Bottom center",
+        }
+    }
+
+    /// Top-left corner of a `size` overlay placed at this anchor inside `frame`,
+    /// `margin` away from the edges. Everything is in video pixels.
+    pub fn position(&self, frame: egui::Vec2, size: egui::Vec2, margin: f32) -> egui::Pos2 {
+        let left = margin;
+        let right = frame.x - size.x - margin;
+        let top = margin;
+        let bottom = frame.y - size.y - margin;
+        match self {
+            PipAnchor::TopLeft => egui::pos2(left, top),
+            PipAnchor::TopRight => egui::pos2(right, top),
+            PipAnchor::BottomLeft => egui::pos2(left, bottom),
+            PipAnchor::BottomRight => egui::pos2(right, bottom),
+            PipAnchor::BottomCenter => egui::pos2((frame.x - size.x) / 2.0, bottom),
+        }
+    }
+}
+
+/// The anchor closest to `pos`, if it is within `distance` video pixels.
+pub fn snap(
+    pos: egui::Pos2,
+    frame: egui::Vec2,
+    size: egui::Vec2,
+    margin: f32,
+    distance: f32,
+) -> Option<PipAnchor> {
+    PipAnchor::ALL
+        .into_iter()
+        .map(|anchor| (anchor, anchor.position(frame, size, margin).distance(pos)))
+        .filter(|(_, d)| *d <= distance)
+        .min_by(|a, b| a.1.total_cmp(&b.1))
+        .map(|(anchor, _)| anchor)
+}
+
+/// Keep a `size` overlay at `pos` inside the frame.
+pub fn clamp(pos: egui::Pos2, frame: egui::Vec2, size: egui::Vec2) -> egui::Pos2 {
+    egui::pos2(
+        pos.x.clamp(0.0, (frame.x - size.x).max(0.0)),
+        pos.y.clamp(0.0, (frame.y - size.y).max(0.0)),
+    )
+}
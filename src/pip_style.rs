@@ -0,0 +1,218 @@
+/// Outline of the webcam overlay.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PipShape {
+    Rectangle,
+    RoundedSquare,
+    Circle,
+}
+
+impl PipShape {
+    pub const ALL: [PipShape; 3] = [
+        PipShape::Rectangle,
+        PipShape::RoundedSquare,
+        PipShape::Circle,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            PipShape::Rectangle => "Rectangle",
+            PipShape::RoundedSquare => "Rounded square",
+            PipShape::Circle => "Circle",
+        }
+    }
+
+    /// Shapes that can crop the camera image to a square at its centre.
+    pub fn is_square(&self) -> bool {
+        matches!(self, PipShape::RoundedSquare | PipShape::Circle)
+    }
+}
+
+/// Styling baked into the PiP frames. The preview and the recording both draw
+/// the output of `render`, so they can't drift apart. Sizes are in PiP frame pixels.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PipStyle {
+    pub shape: PipShape,
+    /// Crop square shapes from the centre instead of stretching them to the frame.
+    pub crop_to_fill: bool,
+    pub corner_radius: f32,
+    pub border_width: f32,
+    pub border_color: [u8; 4],
+    pub shadow: bool,
+    pub shadow_blur: f32,
+    pub shadow_offset: [f32; 2],
+    pub shadow_color: [u8; 4],
+}
+
+impl Default for PipStyle {
+    fn default() -> Self {
+        Self {
+            shape: PipShape::Rectangle,
+            crop_to_fill: true,
+            corner_radius: 8.0,
+            border_width: 0.0,
+            border_color: [255, 255, 255, 255],
+            shadow: false,
+            shadow_blur: 12.0,
+            shadow_offset: [0.0, 4.0],
+            shadow_color: [0, 0, 0, 160],
+        }
+    }
+}
+
+impl PipStyle {
+    /// Transparent margin around the shape that leaves room for the shadow.
+    pub fn padding(&self) -> i32 {
+        if !self.shadow {
+            return 0;
+        }
+        let offset = self.shadow_offset[0].abs().max(self.shadow_offset[1].abs());
+        (self.shadow_blur + offset).ceil() as i32
+    }
+
+    pub fn crops_square(&self) -> bool {
+        self.shape.is_square() && self.crop_to_fill
+    }
+
+    /// Aspect ratio of the visible shape for a `width` x `height` camera, 16:9
+    /// until its size is known.
+    pub fn shape_aspect(&self, width: i32, height: i32) -> f32 {
+        if self.crops_square() {
+            1.0
+        } else if width > 0 && height > 0 {
+            width as f32 / height as f32
+        } else {
+            16.0 / 9.0
+        }
+    }
+
+    /// Width to capture a `width` x `height` camera at for a shape `shape_width`
+    /// pixels wide. Even widths keep the converters happy.
+    pub fn capture_width(&self, shape_width: f32, width: i32, height: i32) -> i32 {
+        // A square crop has to cover the shorter side of the frame
+        let mut capture = shape_width;
+        if self.crops_square() && height > 0 && width > height {
+            capture *= width as f32 / height as f32;
+        }
+        ((capture.round() as i32) / 2 * 2).max(2)
+    }
+
+    fn radius(&self, width: i32, height: i32) -> f32 {
+        let max = width.min(height) as f32 / 2.0;
+        match self.shape {
+            PipShape::Circle => max,
+            _ => self.corner_radius.clamp(0.0, max),
+        }
+    }
+}
+
+/// Size of the visible camera image for `style`, cropped from a `width` x `height` frame.
+pub fn content_size(width: i32, height: i32, style: &PipStyle) -> (i32, i32) {
+    if style.crops_square() {
+        let side = width.min(height);
+        (side, side)
+    } else {
+        (width, height)
+    }
+}
+
+/// A styled PiP frame: RGBA with straight alpha, `padding` pixels of margin on
+/// every side of the shape.
+pub struct StyledFrame {
+    pub data: Vec<u8>,
+    pub width: i32,
+    pub height: i32,
+    pub padding: i32,
+}
+
+/// Signed distance from `p` (relative to the centre) to a rounded box.
+fn rounded_box_sdf(p: [f32; 2], half: [f32; 2], radius: f32) -> f32 {
+    let qx = p[0].abs() - (half[0] - radius);
+    let qy = p[1].abs() - (half[1] - radius);
+    let outside = (qx.max(0.0).powi(2) + qy.max(0.0).powi(2)).sqrt();
+    outside + qx.max(qy).min(0.0) - radius
+}
+
+fn smoothstep(edge0: f32, edge1: f32, x: f32) -> f32 {
+    if edge0 == edge1 {
+        return if x < edge0 { 0.0 } else { 1.0 };
+    }
+    let t = ((x - edge0) / (edge1 - edge0)).clamp(0.0, 1.0);
+    t * t * (3.0 - 2.0 * t)
+}
+
+/// Apply shape mask, border and drop shadow to a raw RGBA camera frame.
+pub fn render(src: &[u8], src_width: i32, src_height: i32, style: &PipStyle) -> StyledFrame {
+    let (content_width, content_height) = content_size(src_width, src_height, style);
+    let crop_x = (src_width - content_width) / 2;
+    let crop_y = (src_height - content_height) / 2;
+
+    let padding = style.padding();
+    let width = content_width + padding * 2;
+    let height = content_height + padding * 2;
+
+    let half = [content_width as f32 / 2.0, content_height as f32 / 2.0];
+    let center = [padding as f32 + half[0], padding as f32 + half[1]];
+    let radius = style.radius(content_width, content_height);
+    let border = style.border_width.max(0.0);
+
+    let mut data = vec![0u8; (width * height * 4) as usize];
+    for y in 0..height {
+        for x in 0..width {
+            let p = [x as f32 + 0.5 - center[0], y as f32 + 0.5 - center[1]];
+            let dist = rounded_box_sdf(p, half, radius);
+
+            // Shape: camera image inside, border along the edge, antialiased
+            let mut fg = [0.0f32; 4];
+            let coverage = (0.5 - dist).clamp(0.0, 1.0);
+            if coverage > 0.0 {
+                let sx = (x - padding + crop_x).clamp(0, src_width - 1);
+                let sy = (y - padding + crop_y).clamp(0, src_height - 1);
+                let idx = ((sy * src_width + sx) * 4) as usize;
+                let content = [src[idx], src[idx + 1], src[idx + 2], src[idx + 3]];
+
+                let inner = if border > 0.0 {
+                    (0.5 - (dist + border)).clamp(0.0, 1.0)
+                } else {
+                    1.0
+                };
+                fg = std::array::from_fn(|c| {
+                    style.border_color[c] as f32 * (1.0 - inner) + content[c] as f32 * inner
+                });
+                fg[3] *= coverage;
+            }
+
+            // Shadow underneath the shape
+            let mut shadow_alpha = 0.0;
+            if style.shadow {
+                let sp = [p[0] - style.shadow_offset[0], p[1] - style.shadow_offset[1]];
+                let shadow_dist = rounded_box_sdf(sp, half, radius);
+                let blur = style.shadow_blur.max(0.0);
+                shadow_alpha =
+                    (1.0 - smoothstep(-blur, blur, shadow_dist)) * style.shadow_color[3] as f32;
+            }
+
+            // Shape over shadow, straight alpha
+            let fg_a = fg[3] / 255.0;
+            let shadow_a = shadow_alpha / 255.0;
+            let out_a = fg_a + shadow_a * (1.0 - fg_a);
+            if out_a <= 0.0 {
+                continue;
+            }
+
+            let idx = ((y * width + x) * 4) as usize;
+            for (c, out) in data[idx..idx + 3].iter_mut().enumerate() {
+                let value =
+                    (fg[c] * fg_a + style.shadow_color[c] as f32 * shadow_a * (1.0 - fg_a)) / out_a;
+                *out = value.round().clamp(0.0, 255.0) as u8;
+            }
+            data[idx + 3] = (out_a * 255.0).round() as u8;
+        }
+    }
+
+    StyledFrame {
+        data,
+        width,
+        height,
+        padding,
+    }
+}
@@ -0,0 +1,168 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum MaskEffect {
+    Blur,
+    Pixelate,
+}
+
+impl MaskEffect {
+    pub const ALL: [MaskEffect; 2] = [MaskEffect::Blur, MaskEffect::Pixelate];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            MaskEffect::Blur => "Blur",
+            MaskEffect::Pixelate => "Pixelate",
+        }
+    }
+}
+
+/// A region of the main video hidden from the preview and the recording.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PrivacyMask {
+    /// Left, top, right and bottom as fractions of the frame, so masks survive
+    /// resolution changes.
+    pub rect: [f32; 4],
+    pub effect: MaskEffect,
+    /// Only applied while masks are toggled on by hotkey, otherwise always on.
+    pub hotkey: bool,
+}
+
+impl PrivacyMask {
+    /// Mask spanning the two corners, given as fractions of the frame.
+    pub fn new(a: [f32; 2], b: [f32; 2], effect: MaskEffect) -> Self {
+        let clamp = |v: f32| v.clamp(0.0, 1.0);
+        Self {
+            rect: [
+                clamp(a[0].min(b[0])),
+                clamp(a[1].min(b[1])),
+                clamp(a[0].max(b[0])),
+                clamp(a[1].max(b[1])),
+            ],
+            effect,
+            hotkey: false,
+        }
+    }
+
+    /// Pixel bounds as (x0, y0, x1, y1), end exclusive, or None if empty.
+    fn pixel_bounds(&self, width: usize, height: usize) -> Option<(usize, usize, usize, usize)> {
+        let [left, top, right, bottom] = self.rect;
+        let x0 = (left * width as f32).floor() as usize;
+        let y0 = (top * height as f32).floor() as usize;
+        let x1 = ((right * width as f32).ceil() as usize).min(width);
+        let y1 = ((bottom * height as f32).ceil() as usize).min(height);
+        (x1 > x0 && y1 > y0).then_some((x0, y0, x1, y1))
+    }
+}
+
+/// Blur or pixelate the masked regions of an RGBA frame in place.
+pub fn apply(data: &mut [u8], width: usize, height: usize, masks: &[PrivacyMask]) {
+    if data.len() != width * height * 4 {
+        return;
+    }
+
+    // Effects scale with the frame, so text stays unreadable at any resolution
+    let block = (width / 80).max(8);
+    let radius = (width / 160).max(4);
+
+    for mask in masks {
+        let Some(bounds) = mask.pixel_bounds(width, height) else {
+            continue;
+        };
+        match mask.effect {
+            MaskEffect::Pixelate => pixelate(data, width, bounds, block),
+            MaskEffect::Blur => blur(data, width, bounds, radius),
+        }
+    }
+}
+
+fn pixelate(data: &mut [u8], width: usize, bounds: (usize, usize, usize, usize), block: usize) {
+    let (x0, y0, x1, y1) = bounds;
+    for by in (y0..y1).step_by(block) {
+        for bx in (x0..x1).step_by(block) {
+            let ys = by..(by + block).min(y1);
+            let xs = bx..(bx + block).min(x1);
+
+            let mut sum = [0u32; 4];
+            for y in ys.clone() {
+                for x in xs.clone() {
+                    let idx = (y * width + x) * 4;
+                    for (c, total) in sum.iter_mut().enumerate() {
+                        *total += data[idx + c] as u32;
+                    }
+                }
+            }
+
+            let count = (ys.len() * xs.len()) as u32;
+            let average = sum.map(|total| (total / count) as u8);
+            for y in ys.clone() {
+                for x in xs.clone() {
+                    let idx = (y * width + x) * 4;
+                    data[idx..idx + 4].copy_from_slice(&average);
+                }
+            }
+        }
+    }
+}
+
+/// Two rounds of separable box blur, close enough to a gaussian to hide text.
+fn blur(data: &mut [u8], width: usize, bounds: (usize, usize, usize, usize), radius: usize) {
+    let (x0, y0, x1, y1) = bounds;
+    let (w, h) = (x1 - x0, y1 - y0);
+
+    let mut region = Vec::with_capacity(w * h);
+    for y in y0..y1 {
+        for x in x0..x1 {
+            let idx = (y * width + x) * 4;
+            region.push([data[idx], data[idx + 1], data[idx + 2], data[idx + 3]]);
+        }
+    }
+
+    for _ in 0..2 {
+        blur_pass(&mut region, w, h, radius, true);
+        blur_pass(&mut region, w, h, radius, false);
+    }
+
+    for (i, pixel) in region.iter().enumerate() {
+        let idx = ((y0 + i / w) * width + x0 + i % w) * 4;
+        data[idx..idx + 4].copy_from_slice(pixel);
+    }
+}
+
+/// Running-sum box blur along rows or columns, edges clamped to the region.
+fn blur_pass(region: &mut [[u8; 4]], w: usize, h: usize, radius: usize, horizontal: bool) {
+    let (lines, len) = if horizontal { (h, w) } else { (w, h) };
+    let index = |line: usize, i: usize| {
+        if horizontal {
+            line * w + i
+        } else {
+            i * w + line
+        }
+    };
+    let at = |i: isize| i.clamp(0, len as isize - 1) as usize;
+    let r = radius as isize;
+    let window = (2 * radius + 1) as u32;
+
+    let mut line_buf = vec![[0u8; 4]; len];
+    for line in 0..lines {
+        for (i, pixel) in line_buf.iter_mut().enumerate() {
+            *pixel = region[index(line, i)];
+        }
+
+        let mut sum = [0u32; 4];
+        for k in -r..=r {
+            for (c, total) in sum.iter_mut().enumerate() {
+                *total += line_buf[at(k)][c] as u32;
+            }
+        }
+
+        for i in 0..len {
+            region[index(line, i)] = sum.map(|total| (total / window) as u8);
+            let leaving = line_buf[at(i as isize - r)];
+            let entering = line_buf[at(i as isize + r + 1)];
+            for (c, total) in sum.iter_mut().enumerate() {
+                *total = *total + entering[c] as u32 - leaving[c] as u32;
+            }
+        }
+    }
+}
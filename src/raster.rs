@@ -0,0 +1,153 @@
+//! Antialiased drawing straight into RGBA frames, for layers that have to be
+//! part of the recorded video rather than painted by egui.
+
+pub struct Canvas<'a> {
+    data: &'a mut [u8],
+    width: usize,
+    height: usize,
+}
+
+impl<'a> Canvas<'a> {
+    /// None if `data` doesn't hold a `width` x `height` RGBA frame.
+    pub fn new(data: &'a mut [u8], width: usize, height: usize) -> Option<Self> {
+        (data.len() == width * height * 4).then_some(Self {
+            data,
+            width,
+            height,
+        })
+    }
+
+    /// Blend `color` over the pixel at (x, y), scaled by `coverage` from 0 to 1.
+    pub fn blend(&mut self, x: i64, y: i64, color: [u8; 4], coverage: f32) {
+        if x < 0 || y < 0 || x as usize >= self.width || y as usize >= self.height {
+            return;
+        }
+        let alpha = color[3] as f32 / 255.0 * coverage.clamp(0.0, 1.0);
+        if alpha <= 0.0 {
+            return;
+        }
+        let idx = (y as usize * self.width + x as usize) * 4;
+        for (c, value) in self.data[idx..idx + 3].iter_mut().enumerate() {
+            *value = (*value as f32 * (1.0 - alpha) + color[c] as f32 * alpha).round() as u8;
+        }
+    }
+
+    /// Visit every pixel within `reach` of the box from `min` to `max`, passing
+    /// its centre to `shade`, which returns the coverage to blend `color` with.
+    fn shade(
+        &mut self,
+        min: [f32; 2],
+        max: [f32; 2],
+        reach: f32,
+        color: [u8; 4],
+        shade: impl Fn(f32, f32) -> f32,
+    ) {
+        let x0 = (min[0] - reach).floor().max(0.0) as i64;
+        let y0 = (min[1] - reach).floor().max(0.0) as i64;
+        let x1 = ((max[0] + reach).ceil() as i64).min(self.width as i64 - 1);
+        let y1 = ((max[1] + reach).ceil() as i64).min(self.height as i64 - 1);
+        for y in y0..=y1 {
+            for x in x0..=x1 {
+                let coverage = shade(x as f32 + 0.5, y as f32 + 0.5);
+                if coverage > 0.0 {
+                    self.blend(x, y, color, coverage);
+                }
+            }
+        }
+    }
+
+    pub fn fill_circle(&mut self, center: [f32; 2], radius: f32, color: [u8; 4]) {
+        self.shade(center, center, radius + 1.0, color, |x, y| {
+            let dist = ((x - center[0]).powi(2) + (y - center[1]).powi(2)).sqrt();
+            (radius - dist + 0.5).clamp(0.0, 1.0)
+        });
+    }
+
+    pub fn ring(&mut self, center: [f32; 2], radius: f32, thickness: f32, color: [u8; 4]) {
+        let half = thickness / 2.0;
+        self.shade(center, center, radius + half + 1.0, color, |x, y| {
+            let dist = ((x - center[0]).powi(2) + (y - center[1]).powi(2)).sqrt();
+            (half - (dist - radius).abs() + 0.5).clamp(0.0, 1.0)
+        });
+    }
+
+    /// Line from `a` to `b` with round caps.
+    pub fn line(&mut self, a: [f32; 2], b: [f32; 2], thickness: f32, color: [u8; 4]) {
+        let half = thickness / 2.0;
+        let min = [a[0].min(b[0]), a[1].min(b[1])];
+        let max = [a[0].max(b[0]), a[1].max(b[1])];
+        self.shade(min, max, half + 1.0, color, |x, y| {
+            segment_coverage(a, b, half, x, y)
+        });
+    }
+
+    /// Connected lines through each of `paths`. Every pixel is blended once,
+    /// so translucent strokes don't darken where segments overlap.
+    pub fn stroke(&mut self, paths: &[&[[f32; 2]]], thickness: f32, color: [u8; 4]) {
+        let half = thickness / 2.0;
+        let reach = half + 1.0;
+        let mut points = paths.iter().flat_map(|path| path.iter());
+        let Some(first) = points.next() else {
+            return;
+        };
+        let (mut min, mut max) = (*first, *first);
+        for p in points {
+            min = [min[0].min(p[0]), min[1].min(p[1])];
+            max = [max[0].max(p[0]), max[1].max(p[1])];
+        }
+
+        // Coverage of the whole stroke's box, end exclusive
+        let clip = |v: f32, len: usize| (v.max(0.0) as usize).min(len);
+        let (x0, y0) = (
+            clip((min[0] - reach).floor(), self.width),
+            clip((min[1] - reach).floor(), self.height),
+        );
+        let (x1, y1) = (
+            clip((max[0] + reach).ceil() + 1.0, self.width),
+            clip((max[1] + reach).ceil() + 1.0, self.height),
+        );
+        if x1 <= x0 || y1 <= y0 {
+            return;
+        }
+        let box_width = x1 - x0;
+        let mut coverage = vec![0.0f32; box_width * (y1 - y0)];
+
+        for path in paths {
+            // A single point is drawn as a dot
+            let segments: Vec<([f32; 2], [f32; 2])> = match path {
+                [] => continue,
+                [p] => vec![(*p, *p)],
+                _ => path.windows(2).map(|w| (w[0], w[1])).collect(),
+            };
+            for (a, b) in segments {
+                let sx0 = clip((a[0].min(b[0]) - reach).floor(), x1).max(x0);
+                let sy0 = clip((a[1].min(b[1]) - reach).floor(), y1).max(y0);
+                let sx1 = clip((a[0].max(b[0]) + reach).ceil() + 1.0, x1);
+                let sy1 = clip((a[1].max(b[1]) + reach).ceil() + 1.0, y1);
+                for y in sy0..sy1 {
+                    for x in sx0..sx1 {
+                        let cov = segment_coverage(a, b, half, x as f32 + 0.5, y as f32 + 0.5);
+                        let cell = &mut coverage[(y - y0) * box_width + x - x0];
+                        *cell = cell.max(cov);
+                    }
+                }
+            }
+        }
+
+        for (i, cov) in coverage.into_iter().enumerate() {
+            if cov > 0.0 {
+                let (x, y) = (x0 + i % box_width, y0 + i / box_width);
+                self.blend(x as i64, y as i64, color, cov);
+            }
+        }
+    }
+}
+
+/// Coverage of the pixel centred at (x, y) by a round-capped segment `half` wide on each side.
+fn segment_coverage(a: [f32; 2], b: [f32; 2], half: f32, x: f32, y: f32) -> f32 {
+    let d = [b[0] - a[0], b[1] - a[1]];
+    let length_sq = (d[0] * d[0] + d[1] * d[1]).max(f32::EPSILON);
+    let t = (((x - a[0]) * d[0] + (y - a[1]) * d[1]) / length_sq).clamp(0.0, 1.0);
+    let dist = ((x - a[0] - d[0] * t).powi(2) + (y - a[1] - d[1] * t).powi(2)).sqrt();
+    (half - dist + 0.5).clamp(0.0, 1.0)
+}
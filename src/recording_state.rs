@@ -0,0 +1,300 @@
+use std::time::{Duration, Instant};
+
+/// Lifecycle of a recording. The UI and hotkeys only ever act on this state;
+/// pipelines report back through bus events which are fed into `transition`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum RecordingState {
+    Idle,
+    Countdown,
+    Starting,
+    Recording,
+    Paused,
+    Finalizing,
+    Failed(String),
+}
+
+/// Things that can happen to a recording, either from the user or the bus.
+#[derive(Debug, Clone, PartialEq)]
+pub enum RecordingEvent {
+    // user actions
+    Countdown,
+    Cancel,
+    Start,
+    Pause,
+    Resume,
+    Stop,
+    // pipeline bus events
+    PipelinePlaying,
+    Eos,
+    Error(String),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct InvalidTransition {
+    pub from: RecordingState,
+    pub event: RecordingEvent,
+}
+
+impl std::fmt::Display for InvalidTransition {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "invalid transition {:?} on {:?}", self.from, self.event)
+    }
+}
+
+impl std::error::Error for InvalidTransition {}
+
+impl RecordingState {
+    pub fn transition(&self, event: RecordingEvent) -> Result<RecordingState, InvalidTransition> {
+        use RecordingEvent as E;
+        use RecordingState as S;
+
+        let next = match (self, &event) {
+            (S::Idle | S::Failed(_), E::Countdown) => S::Countdown,
+            (S::Countdown, E::Cancel) => S::Idle,
+            (S::Idle | S::Failed(_) | S::Countdown, E::Start) => S::Starting,
+            (S::Starting, E::PipelinePlaying) => S::Recording,
+            (S::Recording, E::Pause) => S::Paused,
+            (S::Paused, E::Resume) => S::Recording,
+            (S::Starting | S::Recording | S::Paused, E::Stop) => S::Finalizing,
+            (S::Finalizing, E::Eos) => S::Idle,
+            (S::Starting | S::Recording | S::Paused | S::Finalizing, E::Error(msg)) => {
+                S::Failed(msg.clone())
+            }
+            _ => {
+                return Err(InvalidTransition {
+                    from: self.clone(),
+                    event,
+                })
+            }
+        };
+
+        Ok(next)
+    }
+
+    /// A recording pipeline exists and owns the output files.
+    pub fn is_active(&self) -> bool {
+        matches!(
+            self,
+            RecordingState::Starting
+                | RecordingState::Recording
+                | RecordingState::Paused
+                | RecordingState::Finalizing
+        )
+    }
+
+    pub fn can_start(&self) -> bool {
+        matches!(self, RecordingState::Idle | RecordingState::Failed(_))
+    }
+
+    pub fn can_stop(&self) -> bool {
+        matches!(
+            self,
+            RecordingState::Starting | RecordingState::Recording | RecordingState::Paused
+        )
+    }
+}
+
+/// Elapsed recording time, excluding time spent paused.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct RecordingTimer {
+    running_since: Option<Instant>,
+    accumulated: Duration,
+}
+
+impl RecordingTimer {
+    pub fn reset(&mut self) {
+        self.running_since = None;
+        self.accumulated = Duration::ZERO;
+    }
+
+    pub fn resume(&mut self) {
+        if self.running_since.is_none() {
+            self.running_since = Some(Instant::now());
+        }
+    }
+
+    pub fn pause(&mut self) {
+        if let Some(since) = self.running_since.take() {
+            self.accumulated += since.elapsed();
+        }
+    }
+
+    pub fn elapsed(&self) -> Duration {
+        self.accumulated
+            + self
+                .running_since
+                .map(|since| since.elapsed())
+                .unwrap_or_default()
+    }
+}
+
+pub fn format_duration(duration: Duration) -> String {
+    let secs = duration.as_secs();
+    if secs >= 3600 {
+        format!("{}:{:02}:{:02}", secs / 3600, (secs / 60) % 60, secs % 60)
+    } else {
+        format!("{:02}:{:02}", secs / 60, secs % 60)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use RecordingEvent as E;
+    use RecordingState as S;
+
+    fn failed() -> S {
+        S::Failed("boom".to_string())
+    }
+
+    #[test]
+    fn records_through_the_full_lifecycle() {
+        let mut state = S::Idle;
+        for (event, expected) in [
+            (E::Start, S::Starting),
+            (E::PipelinePlaying, S::Recording),
+            (E::Pause, S::Paused),
+            (E::Resume, S::Recording),
+            (E::Pause, S::Paused),
+            (E::Stop, S::Finalizing),
+            (E::Eos, S::Idle),
+        ] {
+            state = state.transition(event).unwrap();
+            assert_eq!(state, expected);
+        }
+    }
+
+    #[test]
+    fn stops_from_every_running_state() {
+        for from in [S::Starting, S::Recording, S::Paused] {
+            assert_eq!(from.transition(E::Stop), Ok(S::Finalizing));
+        }
+    }
+
+    #[test]
+    fn fails_from_every_active_state() {
+        for from in [S::Starting, S::Recording, S::Paused, S::Finalizing] {
+            assert_eq!(from.transition(E::Error("boom".to_string())), Ok(failed()));
+        }
+    }
+
+    #[test]
+    fn restarts_after_a_failure() {
+        assert_eq!(failed().transition(E::Start), Ok(S::Starting));
+    }
+
+    #[test]
+    fn rejects_invalid_pairs() {
+        for (from, event) in [
+            (S::Idle, E::Stop),
+            (S::Idle, E::Pause),
+            (S::Idle, E::Eos),
+            (S::Idle, E::Error("boom".to_string())),
+            (S::Starting, E::Start),
+            (S::Starting, E::Pause),
+            (S::Recording, E::Start),
+            (S::Recording, E::Resume),
+            (S::Recording, E::PipelinePlaying),
+            (S::Paused, E::Pause),
+            (S::Finalizing, E::Stop),
+            (S::Finalizing, E::Start),
+            (failed(), E::Stop),
+            (failed(), E::Eos),
+        ] {
+            assert_eq!(
+                from.transition(event.clone()),
+                Err(InvalidTransition { from, event })
+            );
+        }
+    }
+
+    #[test]
+    fn reports_what_each_state_allows() {
+        for (state, can_start, can_stop, is_active) in [
+            (S::Idle, true, false, false),
+            (S::Starting, false, true, true),
+            (S::Recording, false, true, true),
+            (S::Paused, false, true, true),
+            (S::Finalizing, false, false, true),
+            (failed(), true, false, false),
+        ] {
+            assert_eq!(state.can_start(), can_start, "{:?}", state);
+            assert_eq!(state.can_stop(), can_stop, "{:?}", state);
+            assert_eq!(state.is_active(), is_active, "{:?}", state);
+        }
+    }
+
+    #[test]
+    fn counts_down_before_starting() {
+        let state = S::Idle.transition(E::Countdown).unwrap();
+        assert_eq!(state, S::Countdown);
+        assert_eq!(state.transition(E::Start), Ok(S::Starting));
+        assert_eq!(failed().transition(E::Countdown), Ok(S::Countdown));
+    }
+
+    #[test]
+    fn cancels_the_countdown() {
+        assert_eq!(S::Countdown.transition(E::Cancel), Ok(S::Idle));
+        assert!(S::Idle.transition(E::Cancel).is_err());
+        assert!(S::Recording.transition(E::Cancel).is_err());
+    }
+
+    #[test]
+    fn rejects_other_events_while_counting_down() {
+        for event in [
+            E::Countdown,
+            E::Pause,
+            E::Resume,
+            E::Stop,
+            E::PipelinePlaying,
+            E::Eos,
+            E::Error("boom".to_string()),
+        ] {
+            assert_eq!(
+                S::Countdown.transition(event.clone()),
+                Err(InvalidTransition {
+                    from: S::Countdown,
+                    event
+                })
+            );
+        }
+        assert!(!S::Countdown.can_start());
+        assert!(!S::Countdown.can_stop());
+        assert!(!S::Countdown.is_active());
+    }
+
+    #[test]
+    fn timer_skips_paused_time() {
+        let step = Duration::from_millis(20);
+        let mut timer = RecordingTimer::default();
+        assert_eq!(timer.elapsed(), Duration::ZERO);
+
+        timer.resume();
+        std::thread::sleep(step);
+        timer.pause();
+        let first = timer.elapsed();
+        assert!(first >= step);
+
+        std::thread::sleep(step);
+        assert_eq!(timer.elapsed(), first);
+        timer.pause();
+        assert_eq!(timer.elapsed(), first);
+
+        timer.resume();
+        std::thread::sleep(step);
+        assert!(timer.elapsed() >= first + step);
+
+        timer.reset();
+        assert_eq!(timer.elapsed(), Duration::ZERO);
+    }
+
+    #[test]
+    fn formats_minutes_and_hours() {
+        assert_eq!(format_duration(Duration::ZERO), "00:00");
+        assert_eq!(format_duration(Duration::from_millis(59_900)), "00:59");
+        assert_eq!(format_duration(Duration::from_secs(754)), "12:34");
+        assert_eq!(format_duration(Duration::from_secs(3599)), "59:59");
+        assert_eq!(format_duration(Duration::from_secs(3600)), "1:00:00");
+        assert_eq!(format_duration(Duration::from_secs(37_230)), "10:20:30");
+    }
+}
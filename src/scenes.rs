@@ -0,0 +1,170 @@
+use crate::overlays::Overlays;
+use crate::pip_layout::PipAnchor;
+use crate::split_layout::SplitLayout;
+use eframe::egui;
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, Instant};
+
+/// Number keys 1 to 9 switch to the scene at that position.
+pub const HOTKEYS: [egui::Key; 9] = [
+    egui::Key::Num1,
+    egui::Key::Num2,
+    egui::Key::Num3,
+    egui::Key::Num4,
+    egui::Key::Num5,
+    egui::Key::Num6,
+    egui::Key::Num7,
+    egui::Key::Num8,
+    egui::Key::Num9,
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum SceneTransition {
+    Cut,
+    Crossfade,
+}
+
+impl SceneTransition {
+    pub const ALL: [SceneTransition; 2] = [SceneTransition::Cut, SceneTransition::Crossfade];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            SceneTransition::Cut => "Cut",
+            SceneTransition::Crossfade => "Crossfade",
+        }
+    }
+}
+
+/// Sources, PiP placement, layout and overlays, recalled together.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Scene {
+    pub name: String,
+    /// Device labels, so scenes follow devices across restarts.
+    pub main_source: Option<String>,
+    /// None hides the PiP.
+    pub pip_source: Option<String>,
+    pub pip_anchor: Option<PipAnchor>,
+    /// Top-left of the PiP canvas as a fraction of the frame, used without an anchor.
+    pub pip_offset: [f32; 2],
+    /// Width of the visible PiP shape in video pixels.
+    pub pip_width: f32,
+    pub pip_margin: f32,
+    pub overlays: Overlays,
+    /// Scenes stored before split layouts float the PiP.
+    #[serde(default)]
+    pub layout: SplitLayout,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Scenes {
+    pub scenes: Vec<Scene>,
+    pub transition: SceneTransition,
+    /// Crossfade length in seconds.
+    pub fade_duration: f32,
+}
+
+impl Default for Scenes {
+    fn default() -> Self {
+        Self {
+            scenes: Vec::new(),
+            transition: SceneTransition::Cut,
+            fade_duration: 0.5,
+        }
+    }
+}
+
+/// Fades the last picture of the previous scene out over the new one. The
+/// picture is fed to the compositor's top pad, whose alpha follows `alpha`.
+pub struct Crossfade {
+    /// The same picture for the preview, loaded on the next repaint.
+    pub texture: Option<egui::TextureHandle>,
+    started: Instant,
+    duration: Duration,
+}
+
+impl Crossfade {
+    pub fn new(duration: f32) -> Self {
+        Self {
+            texture: None,
+            started: Instant::now(),
+            duration: Duration::from_secs_f32(duration.max(0.05)),
+        }
+    }
+
+    /// From 1 when the fade starts to 0 when it is done.
+    pub fn alpha(&self) -> f32 {
+        1.0 - (self.started.elapsed().as_secs_f32() / self.duration.as_secs_f32()).min(1.0)
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.started.elapsed() >= self.duration
+    }
+}
+
+/// What the user asked for in the scenes panel.
+pub enum SceneAction {
+    Switch(usize),
+    /// Store the current setup, as a new scene or over an existing one.
+    Capture(Option<usize>),
+    Remove(usize),
+}
+
+/// Scene list with switch, update and remove buttons, plus the transition settings.
+pub fn controls(
+    ui: &mut egui::Ui,
+    scenes: &mut Scenes,
+    active: Option<usize>,
+) -> Option<SceneAction> {
+    let mut action = None;
+
+    for (idx, scene) in scenes.scenes.iter_mut().enumerate() {
+        ui.horizontal(|ui| {
+            let hotkey = if idx < HOTKEYS.len() {
+                (idx + 1).to_string()
+            } else {
+                "-".to_string()
+            };
+            if ui
+                .selectable_label(active == Some(idx), hotkey)
+                .on_hover_text("Switch to this scene")
+                .clicked()
+            {
+                action = Some(SceneAction::Switch(idx));
+            }
+            ui.add(egui::TextEdit::singleline(&mut scene.name).desired_width(120.0));
+            if ui
+                .small_button("Update")
+                .on_hover_text("Store the current setup in this scene")
+                .clicked()
+            {
+                action = Some(SceneAction::Capture(Some(idx)));
+            }
+            if ui.small_button("Remove").clicked() {
+                action = Some(SceneAction::Remove(idx));
+            }
+        });
+    }
+    if ui.button("Add scene").clicked() {
+        action = Some(SceneAction::Capture(None));
+    }
+
+    ui.horizontal(|ui| {
+        egui::ComboBox::from_id_salt("scene_transition")
+            .selected_text(scenes.transition.label())
+            .show_ui(ui, |ui| {
+                for transition in SceneTransition::ALL {
+                    ui.selectable_value(&mut scenes.transition, transition, transition.label());
+                }
+            });
+        if scenes.transition == SceneTransition::Crossfade {
+            ui.add(
+                egui::DragValue::new(&mut scenes.fade_duration)
+                    .range(0.1..=3.0)
+                    .speed(0.05)
+                    .suffix(" s"),
+            );
+        }
+    });
+    action
+}
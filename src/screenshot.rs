@@ -0,0 +1,197 @@
+use eframe::egui;
+use image::{imageops, DynamicImage, ImageFormat, Rgba, RgbaImage};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ScreenshotFormat {
+    Png,
+    Jpeg,
+    WebP,
+}
+
+impl ScreenshotFormat {
+    pub const ALL: [ScreenshotFormat; 3] = [
+        ScreenshotFormat::Png,
+        ScreenshotFormat::Jpeg,
+        ScreenshotFormat::WebP,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            ScreenshotFormat::Png => "PNG",
+            ScreenshotFormat::Jpeg => "JPEG",
+            ScreenshotFormat::WebP => "WebP",
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ScreenshotFormat::Png => "png",
+            ScreenshotFormat::Jpeg => "jpg",
+            ScreenshotFormat::WebP => "webp",
+        }
+    }
+
+    fn image_format(&self) -> ImageFormat {
+        match self {
+            ScreenshotFormat::Png => ImageFormat::Png,
+            ScreenshotFormat::Jpeg => ImageFormat::Jpeg,
+            ScreenshotFormat::WebP => ImageFormat::WebP,
+        }
+    }
+}
+
+/// Scale `overlay` into `rect` (in `base` pixels) and blend it on top.
+pub fn composite(base: &mut RgbaImage, overlay: &RgbaImage, rect: egui::Rect) {
+    let width = rect.width().round().max(1.0) as u32;
+    let height = rect.height().round().max(1.0) as u32;
+    let scaled = imageops::resize(overlay, width, height, imageops::FilterType::Triangle);
+    imageops::overlay(
+        base,
+        &scaled,
+        rect.left().round() as i64,
+        rect.top().round() as i64,
+    );
+}
+
+pub fn save(image: &RgbaImage, format: ScreenshotFormat, path: &str) -> Result<(), anyhow::Error> {
+    match format {
+        // JPEG has no alpha channel
+        ScreenshotFormat::Jpeg => DynamicImage::ImageRgba8(image.clone())
+            .to_rgb8()
+            .save_with_format(path, format.image_format())?,
+        _ => image.save_with_format(path, format.image_format())?,
+    }
+    Ok(())
+}
+
+/// Stamp a round brush along the segment from `a` to `b`.
+fn draw_line(image: &mut RgbaImage, a: egui::Pos2, b: egui::Pos2, width: f32, color: Rgba<u8>) {
+    let radius = (width / 2.0).max(0.5);
+    let steps = ((b - a).length() / (radius / 2.0)).ceil().max(1.0) as usize;
+    for step in 0..=steps {
+        let center = a.lerp(b, step as f32 / steps as f32);
+        let min_x = (center.x - radius).floor().max(0.0) as u32;
+        let min_y = (center.y - radius).floor().max(0.0) as u32;
+        let max_x = ((center.x + radius).ceil() as u32).min(image.width().saturating_sub(1));
+        let max_y = ((center.y + radius).ceil() as u32).min(image.height().saturating_sub(1));
+        for y in min_y..=max_y {
+            for x in min_x..=max_x {
+                if egui::pos2(x as f32 + 0.5, y as f32 + 0.5).distance(center) <= radius {
+                    image.put_pixel(x, y, color);
+                }
+            }
+        }
+    }
+}
+
+/// Quick freehand markup of a saved screenshot before sharing it.
+pub struct AnnotateView {
+    image: RgbaImage,
+    path: String,
+    format: ScreenshotFormat,
+    texture: Option<egui::TextureHandle>,
+    /// Strokes in image pixels, the last one is the one being drawn.
+    strokes: Vec<Vec<egui::Pos2>>,
+    color: egui::Color32,
+    width: f32,
+}
+
+impl AnnotateView {
+    pub fn new(image: RgbaImage, path: String, format: ScreenshotFormat) -> Self {
+        Self {
+            image,
+            path,
+            format,
+            texture: None,
+            strokes: Vec::new(),
+            color: egui::Color32::from_rgb(255, 80, 80),
+            width: 6.0,
+        }
+    }
+
+    /// Shows the annotate window, returns false once it has been closed.
+    pub fn show(&mut self, ctx: &egui::Context) -> bool {
+        let mut open = true;
+        let mut close = false;
+
+        let texture = self.texture.get_or_insert_with(|| {
+            ctx.load_texture(
+                "screenshot-annotate",
+                egui::ColorImage::from_rgba_unmultiplied(
+                    [self.image.width() as usize, self.image.height() as usize],
+                    self.image.as_raw(),
+                ),
+                egui::TextureOptions::default(),
+            )
+        });
+        let image_size = egui::vec2(self.image.width() as f32, self.image.height() as f32);
+
+        egui::Window::new("Annotate screenshot")
+            .open(&mut open)
+            .default_size(egui::vec2(640.0, 420.0))
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    ui.color_edit_button_srgba(&mut self.color);
+                    ui.add(egui::Slider::new(&mut self.width, 1.0..=30.0).text("Width"));
+                    if ui.button("Undo").clicked() {
+                        self.strokes.pop();
+                    }
+                    if ui.button("Clear").clicked() {
+                        self.strokes.clear();
+                    }
+                    if ui.button("Save").clicked() {
+                        let color = Rgba([self.color.r(), self.color.g(), self.color.b(), 255]);
+                        let mut annotated = self.image.clone();
+                        for stroke in &self.strokes {
+                            for pair in stroke.windows(2) {
+                                draw_line(&mut annotated, pair[0], pair[1], self.width, color);
+                            }
+                        }
+                        match save(&annotated, self.format, &self.path) {
+                            Ok(()) => {
+                                println!("Saved annotated screenshot to {}", self.path);
+                                close = true;
+                            }
+                            Err(e) => eprintln!("Failed to save screenshot: {:?}", e),
+                        }
+                    }
+                });
+
+                let available = ui.available_size();
+                let scale = (available.x / image_size.x)
+                    .min(available.y / image_size.y)
+                    .min(1.0);
+                let response = ui.add(
+                    egui::Image::new(&*texture)
+                        .fit_to_exact_size(image_size * scale)
+                        .sense(egui::Sense::drag()),
+                );
+
+                let to_image = |pos: egui::Pos2| ((pos - response.rect.min) / scale).to_pos2();
+                if response.drag_started() {
+                    self.strokes.push(Vec::new());
+                }
+                if response.dragged() {
+                    if let (Some(pos), Some(stroke)) =
+                        (response.interact_pointer_pos(), self.strokes.last_mut())
+                    {
+                        stroke.push(to_image(pos));
+                    }
+                }
+
+                let painter = ui.painter_at(response.rect);
+                for stroke in &self.strokes {
+                    let points = stroke
+                        .iter()
+                        .map(|p| response.rect.min + p.to_vec2() * scale)
+                        .collect::<Vec<_>>();
+                    painter.add(egui::Shape::line(
+                        points,
+                        egui::Stroke::new(self.width * scale, self.color),
+                    ));
+                }
+            });
+
+        open && !close
+    }
+}
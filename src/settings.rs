@@ -0,0 +1,67 @@
+use crate::annotations::AnnotationSettings;
+use crate::auto_zoom::ZoomSettings;
+use crate::cursor_fx::CursorEffects;
+use crate::keystrokes::KeystrokeSettings;
+use crate::overlays::Overlays;
+use crate::pip_keyframes::PipTimeline;
+use crate::privacy_mask::PrivacyMask;
+use crate::scenes::Scenes;
+use crate::split_layout::SplitLayout;
+use crate::video_adjust::VideoAdjustments;
+use crate::video_transform::VideoTransform;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+const STORAGE_KEY: &str = "settings";
+
+/// Preferences kept between runs, stored through eframe's persistence.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    /// Keyed by device label, so they follow a camera or display across restarts.
+    pub video_adjustments: HashMap<String, VideoAdjustments>,
+    pub video_transforms: HashMap<String, VideoTransform>,
+    pub privacy_masks: HashMap<String, Vec<PrivacyMask>>,
+    /// Title, timestamp and logo burned into the main video, whatever the source.
+    pub overlays: Overlays,
+    /// Pointer highlight and click ripples, drawn when a display is captured.
+    pub cursor_effects: CursorEffects,
+    /// Key combos shown over the main video for tutorials.
+    pub keystrokes: KeystrokeSettings,
+    /// Tool and style for live drawing on the main video.
+    pub annotations: AnnotationSettings,
+    /// Zoom and pan of recorded screen captures.
+    pub auto_zoom: ZoomSettings,
+    /// Keyframed PiP animation played back while recording.
+    pub pip_timeline: PipTimeline,
+    /// Named setups switched with the number keys.
+    pub scenes: Scenes,
+    /// Floating PiP, or the main video and PiP source side by side.
+    pub split_layout: SplitLayout,
+}
+
+impl Settings {
+    pub fn load(storage: Option<&dyn eframe::Storage>) -> Self {
+        storage
+            .and_then(|storage| eframe::get_value(storage, STORAGE_KEY))
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self, storage: &mut dyn eframe::Storage) {
+        eframe::set_value(storage, STORAGE_KEY, self);
+    }
+
+    pub fn video_adjustments(&self, source: &str) -> VideoAdjustments {
+        self.video_adjustments
+            .get(source)
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    pub fn video_transform(&self, source: &str) -> VideoTransform {
+        self.video_transforms
+            .get(source)
+            .cloned()
+            .unwrap_or_default()
+    }
+}
@@ -0,0 +1,241 @@
+use eframe::egui;
+use image::{imageops, DynamicImage, RgbaImage};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum LayoutMode {
+    /// The second video floats over the main one.
+    Pip,
+    SideBySide,
+    TopBottom,
+}
+
+impl LayoutMode {
+    pub const ALL: [LayoutMode; 3] = [
+        LayoutMode::Pip,
+        LayoutMode::SideBySide,
+        LayoutMode::TopBottom,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            LayoutMode::Pip => "Picture in picture",
+            LayoutMode::SideBySide => "Side by side",
+            LayoutMode::TopBottom => "Top and bottom",
+        }
+    }
+}
+
+/// How the main video and the PiP source share the frame. Split layouts give
+/// each video a cell of its own, placed through the compositor's pad properties.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SplitLayout {
+    pub mode: LayoutMode,
+    /// Share of the frame given to the main video.
+    pub ratio: f32,
+    /// Put the PiP source left of or above the main video.
+    pub swap: bool,
+    /// Space between the videos and around the edges, in video pixels.
+    pub gap: f32,
+    pub background: [u8; 3],
+    /// Image covering the frame over the background color, none when empty.
+    pub background_image: String,
+}
+
+impl Default for SplitLayout {
+    fn default() -> Self {
+        Self {
+            mode: LayoutMode::Pip,
+            ratio: 0.5,
+            swap: false,
+            gap: 0.0,
+            background: [0, 0, 0],
+            background_image: String::new(),
+        }
+    }
+}
+
+impl SplitLayout {
+    pub fn is_split(&self) -> bool {
+        self.mode != LayoutMode::Pip
+    }
+
+    /// Cells of the main video and the PiP source in a frame of `size` pixels,
+    /// or None when the PiP floats over the main video.
+    pub fn cells(&self, size: egui::Vec2) -> Option<(egui::Rect, egui::Rect)> {
+        let inner = egui::Rect::from_min_size(egui::Pos2::ZERO, size).shrink(self.gap);
+        let ratio = self.ratio.clamp(0.1, 0.9);
+        let (first, second) = match self.mode {
+            LayoutMode::Pip => return None,
+            LayoutMode::SideBySide => {
+                let space = (inner.width() - self.gap).max(0.0);
+                let split = if self.swap { 1.0 - ratio } else { ratio };
+                let first =
+                    egui::Rect::from_min_size(inner.min, egui::vec2(space * split, inner.height()));
+                let second = egui::Rect::from_min_max(
+                    egui::pos2(first.right() + self.gap, inner.top()),
+                    inner.max,
+                );
+                (first, second)
+            }
+            LayoutMode::TopBottom => {
+                let space = (inner.height() - self.gap).max(0.0);
+                let split = if self.swap { 1.0 - ratio } else { ratio };
+                let first =
+                    egui::Rect::from_min_size(inner.min, egui::vec2(inner.width(), space * split));
+                let second = egui::Rect::from_min_max(
+                    egui::pos2(inner.left(), first.bottom() + self.gap),
+                    inner.max,
+                );
+                (first, second)
+            }
+        };
+        Some(if self.swap {
+            (second, first)
+        } else {
+            (first, second)
+        })
+    }
+
+    /// The background as a frame of `width` by `height`, with `cover`, the
+    /// image already fitted to the frame, blended over the color.
+    pub fn render_background(
+        &self,
+        cover: Option<&RgbaImage>,
+        width: u32,
+        height: u32,
+    ) -> RgbaImage {
+        let [r, g, b] = self.background;
+        let mut background = RgbaImage::from_pixel(width, height, image::Rgba([r, g, b, 255]));
+        if let Some(cover) = cover {
+            imageops::overlay(&mut background, cover, 0, 0);
+        }
+        background
+    }
+}
+
+/// The background image, decoded on a thread of its own so picking one never
+/// stalls the UI, and kept fitted to the frame so color changes only blend it.
+#[derive(Default)]
+pub struct BackgroundImage {
+    decoded: Arc<Mutex<Decoded>>,
+}
+
+#[derive(Default)]
+struct Decoded {
+    path: String,
+    image: Option<Arc<DynamicImage>>,
+    /// Bumped whenever the image changes, so renders know to redo.
+    revision: u64,
+    /// The image resized to cover a frame of the given size.
+    fitted: Option<((u32, u32), Arc<RgbaImage>)>,
+}
+
+impl BackgroundImage {
+    /// The image at `path` covering a `width` by `height` frame, and its
+    /// revision. None until it is decoded. A path that isn't a file, like one
+    /// still being typed, keeps the last image.
+    pub fn fitted(&self, path: &str, width: u32, height: u32) -> (u64, Option<Arc<RgbaImage>>) {
+        let mut decoded = self.decoded.lock().unwrap();
+        if decoded.path != path && (path.is_empty() || Path::new(path).is_file()) {
+            decoded.path = path.to_string();
+            decoded.image = None;
+            decoded.fitted = None;
+            decoded.revision += 1;
+            if !path.is_empty() {
+                let shared = self.decoded.clone();
+                let path = path.to_string();
+                thread::spawn(move || {
+                    let image = match image::open(&path) {
+                        Ok(image) => image,
+                        Err(e) => {
+                            eprintln!("Failed to load background {}: {:?}", path, e);
+                            return;
+                        }
+                    };
+                    // Another image may have been picked while this one decoded
+                    let mut decoded = shared.lock().unwrap();
+                    if decoded.path == path {
+                        decoded.image = Some(Arc::new(image));
+                        decoded.fitted = None;
+                        decoded.revision += 1;
+                    }
+                });
+            }
+        }
+
+        // Resized once per image and frame size
+        let size = (width, height);
+        if let Some(image) = decoded.image.clone() {
+            if decoded.fitted.as_ref().map(|(at, _)| *at) != Some(size) {
+                let fitted = image
+                    .resize_to_fill(width, height, imageops::FilterType::Triangle)
+                    .to_rgba8();
+                decoded.fitted = Some((size, Arc::new(fitted)));
+            }
+        }
+        let fitted = decoded.fitted.as_ref().map(|(_, fitted)| fitted.clone());
+        (decoded.revision, fitted)
+    }
+}
+
+/// The largest rect of `aspect` centered in `cell`, so videos keep their shape.
+pub fn fit(cell: egui::Rect, aspect: f32) -> egui::Rect {
+    let mut size = cell.size();
+    if size.x / size.y.max(1.0) > aspect {
+        size.x = size.y * aspect;
+    } else {
+        size.y = size.x / aspect;
+    }
+    egui::Rect::from_center_size(cell.center(), size)
+}
+
+/// Mode, split, gap and background controls, returns true when anything changed.
+pub fn controls(ui: &mut egui::Ui, layout: &mut SplitLayout) -> bool {
+    let mut changed = false;
+    egui::ComboBox::from_id_salt("split_layout_mode")
+        .selected_text(layout.mode.label())
+        .show_ui(ui, |ui| {
+            for mode in LayoutMode::ALL {
+                changed |= ui
+                    .selectable_value(&mut layout.mode, mode, mode.label())
+                    .changed();
+            }
+        });
+    if !layout.is_split() {
+        return changed;
+    }
+
+    changed |= ui
+        .add(egui::Slider::new(&mut layout.ratio, 0.1..=0.9).text("Split"))
+        .changed();
+    changed |= ui.checkbox(&mut layout.swap, "Swap sides").changed();
+    changed |= ui
+        .add(
+            egui::Slider::new(&mut layout.gap, 0.0..=120.0)
+                .suffix(" px")
+                .text("Gap"),
+        )
+        .changed();
+    ui.horizontal(|ui| {
+        ui.label("Background");
+        changed |= ui.color_edit_button_srgb(&mut layout.background).changed();
+        // Only a finished path counts, not every keystroke on the way there
+        let path = ui
+            .add(egui::TextEdit::singleline(&mut layout.background_image).hint_text("Image path"));
+        changed |=
+            path.lost_focus() || (path.changed() && Path::new(&layout.background_image).is_file());
+    });
+    if !layout.background_image.is_empty() && !Path::new(&layout.background_image).is_file() {
+        ui.label(
+            egui::RichText::new("Image not found")
+                .size(11.0)
+                .color(egui::Color32::from_rgb(255, 120, 120)),
+        );
+    }
+    changed
+}
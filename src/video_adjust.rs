@@ -0,0 +1,75 @@
+use eframe::egui;
+use gstreamer as gst;
+use gstreamer::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// Color correction for a video source. Source pipelines run their RGBA frames
+/// through `videobalance name=balance ! gamma name=gamma`, which pass frames
+/// through untouched at the default values.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct VideoAdjustments {
+    pub brightness: f64,
+    pub contrast: f64,
+    pub saturation: f64,
+    pub gamma: f64,
+}
+
+impl Default for VideoAdjustments {
+    fn default() -> Self {
+        Self {
+            brightness: 0.0,
+            contrast: 1.0,
+            saturation: 1.0,
+            gamma: 1.0,
+        }
+    }
+}
+
+impl VideoAdjustments {
+    pub fn is_default(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// Update the balance and gamma elements of a running pipeline.
+    pub fn apply(&self, pipeline: &gst::Pipeline) {
+        if let Some(balance) = pipeline.by_name("balance") {
+            balance.set_property("brightness", self.brightness);
+            balance.set_property("contrast", self.contrast);
+            balance.set_property("saturation", self.saturation);
+        }
+        if let Some(gamma) = pipeline.by_name("gamma") {
+            gamma.set_property("gamma", self.gamma);
+        }
+    }
+}
+
+/// Sliders plus a reset button, returns true when anything changed.
+pub fn controls(ui: &mut egui::Ui, adjustments: &mut VideoAdjustments) -> bool {
+    let mut changed = false;
+    changed |= ui
+        .add(egui::Slider::new(&mut adjustments.brightness, -1.0..=1.0).text("Brightness"))
+        .changed();
+    changed |= ui
+        .add(egui::Slider::new(&mut adjustments.contrast, 0.0..=2.0).text("Contrast"))
+        .changed();
+    changed |= ui
+        .add(egui::Slider::new(&mut adjustments.saturation, 0.0..=2.0).text("Saturation"))
+        .changed();
+    changed |= ui
+        .add(
+            egui::Slider::new(&mut adjustments.gamma, 0.1..=3.0)
+                .logarithmic(true)
+                .text("Gamma"),
+        )
+        .changed();
+
+    if ui
+        .add_enabled(!adjustments.is_default(), egui::Button::new("Reset"))
+        .clicked()
+    {
+        *adjustments = VideoAdjustments::default();
+        changed = true;
+    }
+    changed
+}
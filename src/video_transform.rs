@@ -0,0 +1,200 @@
+use eframe::egui;
+use gstreamer as gst;
+use gstreamer::prelude::*;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub enum Rotation {
+    #[default]
+    None,
+    Clockwise90,
+    Rotate180,
+    Clockwise270,
+}
+
+impl Rotation {
+    pub const ALL: [Rotation; 4] = [
+        Rotation::None,
+        Rotation::Clockwise90,
+        Rotation::Rotate180,
+        Rotation::Clockwise270,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Rotation::None => "0°",
+            Rotation::Clockwise90 => "90°",
+            Rotation::Rotate180 => "180°",
+            Rotation::Clockwise270 => "270°",
+        }
+    }
+
+    fn quarter_turns(&self) -> u8 {
+        match self {
+            Rotation::None => 0,
+            Rotation::Clockwise90 => 1,
+            Rotation::Rotate180 => 2,
+            Rotation::Clockwise270 => 3,
+        }
+    }
+}
+
+/// Orientation of a video source. Source pipelines run their frames through
+/// `videoflip name=flip`, which is identity until a transform is applied.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct VideoTransform {
+    pub mirror: bool,
+    /// Bake the mirror into recordings and screenshots, not just the preview.
+    pub mirror_in_recording: bool,
+    pub flip_vertical: bool,
+    pub rotation: Rotation,
+}
+
+impl VideoTransform {
+    /// Mirror the preview image only, the frames themselves stay as captured.
+    pub fn mirrors_preview_only(&self) -> bool {
+        self.mirror && !self.mirror_in_recording
+    }
+
+    /// The single `video-direction` equivalent to flipping, then rotating clockwise.
+    fn video_direction(&self) -> &'static str {
+        // A vertical flip is a horizontal flip turned by 180°
+        let mirror = self.mirror && self.mirror_in_recording;
+        let flipped = mirror != self.flip_vertical;
+        let turns = (self.rotation.quarter_turns() + if self.flip_vertical { 2 } else { 0 }) % 4;
+
+        match (turns, flipped) {
+            (0, false) => "identity",
+            (1, false) => "90r",
+            (2, false) => "180",
+            (_, false) => "90l",
+            (0, true) => "horiz",
+            (1, true) => "ur-ll",
+            (2, true) => "vert",
+            (_, true) => "ul-lr",
+        }
+    }
+
+    /// Where a point of the captured frame ends up once `videoflip` has run,
+    /// both as fractions of their frame.
+    pub fn orient(&self, point: [f32; 2]) -> [f32; 2] {
+        let [mut x, mut y] = point;
+        if self.mirror && self.mirror_in_recording {
+            x = 1.0 - x;
+        }
+        if self.flip_vertical {
+            y = 1.0 - y;
+        }
+        match self.rotation {
+            Rotation::None => [x, y],
+            Rotation::Clockwise90 => [1.0 - y, x],
+            Rotation::Rotate180 => [1.0 - x, 1.0 - y],
+            Rotation::Clockwise270 => [y, 1.0 - x],
+        }
+    }
+
+    /// The transformed frame is as wide as the captured one is tall.
+    pub fn is_sideways(&self) -> bool {
+        self.rotation.quarter_turns() % 2 == 1
+    }
+
+    pub fn apply(&self, pipeline: &gst::Pipeline) {
+        if let Some(flip) = pipeline.by_name("flip") {
+            flip.set_property_from_str("video-direction", self.video_direction());
+        }
+    }
+}
+
+/// Mirror, flip and rotation controls, returns true when anything changed.
+/// Rotating changes the frame size, so it can be locked while recording.
+pub fn controls(ui: &mut egui::Ui, transform: &mut VideoTransform, can_rotate: bool) -> bool {
+    let mut changed = false;
+    ui.horizontal(|ui| {
+        changed |= ui.checkbox(&mut transform.mirror, "Mirror").changed();
+        changed |= ui
+            .add_enabled(
+                transform.mirror,
+                egui::Checkbox::new(&mut transform.mirror_in_recording, "Also in recording"),
+            )
+            .changed();
+    });
+    ui.horizontal(|ui| {
+        changed |= ui.checkbox(&mut transform.flip_vertical, "Flip").changed();
+        ui.add_enabled_ui(can_rotate, |ui| {
+            for rotation in Rotation::ALL {
+                changed |= ui
+                    .selectable_value(&mut transform.rotation, rotation, rotation.label())
+                    .changed();
+            }
+        });
+    });
+    changed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn transform(mirror: bool, flip_vertical: bool, rotation: Rotation) -> VideoTransform {
+        VideoTransform {
+            mirror,
+            mirror_in_recording: true,
+            flip_vertical,
+            rotation,
+        }
+    }
+
+    #[test]
+    fn rotates_corners_clockwise() {
+        let top_left = [0.0, 0.0];
+        assert_eq!(
+            transform(false, false, Rotation::None).orient(top_left),
+            [0.0, 0.0]
+        );
+        assert_eq!(
+            transform(false, false, Rotation::Clockwise90).orient(top_left),
+            [1.0, 0.0]
+        );
+        assert_eq!(
+            transform(false, false, Rotation::Rotate180).orient(top_left),
+            [1.0, 1.0]
+        );
+        assert_eq!(
+            transform(false, false, Rotation::Clockwise270).orient(top_left),
+            [0.0, 1.0]
+        );
+    }
+
+    #[test]
+    fn flips_before_rotating() {
+        let point = [0.25, 0.1];
+        assert_eq!(
+            transform(true, false, Rotation::None).orient(point),
+            [0.75, 0.1]
+        );
+        assert_eq!(
+            transform(false, true, Rotation::None).orient(point),
+            [0.25, 0.9]
+        );
+        assert_eq!(
+            transform(true, false, Rotation::Clockwise90).orient(point),
+            [0.9, 0.75]
+        );
+    }
+
+    #[test]
+    fn ignores_preview_only_mirror() {
+        let preview_only = VideoTransform {
+            mirror: true,
+            ..VideoTransform::default()
+        };
+        assert_eq!(preview_only.orient([0.25, 0.5]), [0.25, 0.5]);
+    }
+
+    #[test]
+    fn reports_sideways_rotations() {
+        assert!(!transform(false, false, Rotation::Rotate180).is_sideways());
+        assert!(transform(false, false, Rotation::Clockwise270).is_sideways());
+    }
+}